@@ -0,0 +1,119 @@
+/*
+ * Reference only: the request parser of the baseline tree (`HttpRequest::new` before
+ * requests borrowed from their read buffer), frozen as it was so the benchmark always
+ * measures against the same thing. It is not part of the crate and is not meant to
+ * follow it: no validation was added, and the method and version enums it used are
+ * replaced by the strings they were parsed from.
+ */
+use std::{collections::HashMap, error::Error};
+
+const MAX_HEADERS_LENGTH: usize = 25;
+
+type RequestLine = (Option<String>, Option<String>, Option<String>);
+
+pub struct BaselineRequest {
+	pub path: String,
+	pub method: String,
+	pub version: String,
+	pub headers: HashMap<String, String>,
+	pub body: Vec<u8>,
+}
+
+impl BaselineRequest {
+	pub fn new(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+		let mut i: usize = 0;
+		let length = bytes.len();
+		
+		// 1. Request Line
+		let (method, path, version): (String, String, String) = {
+			let mut method: Option<String> = None;
+			let mut path: Option<String> = None;
+			let mut version: Option<String> = None;
+			while i < length - 1 {
+				if bytes[i] == 0x0D && bytes[i + 1] == 0x0A {
+					(method, path, version) = Self::parse_request_line(&bytes[..i])?;
+					break;
+				}
+				
+				i += 1;
+			}
+			
+			match (method, path, version) {
+				(Some(method), Some(path), Some(version)) => (method, path, version),
+				_ => return Err("request line not found".into()),
+			}
+		};
+		
+		// 4. Headers
+		let mut j = i;
+		let mut eoh = false;
+		let mut length_of_headers = 0;
+		let mut headers: HashMap<String, String> = HashMap::new();
+		while i < length - 3 {
+			if bytes[i] == 0x0D && bytes[i + 1] == 0x0A {
+				if let Some((key, value)) = Self::parse_header(&bytes[j..i]) {
+					headers.insert(key, value);
+				}
+				
+				if bytes[i + 2] == 0x0D && bytes[i + 3] == 0x0A {
+					eoh = true;
+					break;
+				}
+				
+				j = i;
+				i += 2;
+				length_of_headers += 1;
+			} else {
+				i += 1;
+			}
+		}
+		
+		if length_of_headers > MAX_HEADERS_LENGTH || !eoh {
+			return Err("headers too large".into());
+		}
+		
+		// 5. Body
+		i += 4;
+		let body: Vec<u8> = if matches!(method.as_str(), "POST" | "PUT" | "PATCH") { bytes[i..].to_vec() } else { Vec::new() };
+		
+		Ok(
+			Self {
+				path,
+				method,
+				version,
+				headers,
+				body: body.to_vec(),
+			}
+		)
+	}
+	
+	fn parse_request_line(as_bytes: &[u8]) -> Result<RequestLine, Box<dyn Error>> {
+		let Ok(line) = std::str::from_utf8(as_bytes) else {
+			return Ok((None, None, None));
+		};
+		
+		let mut parts = line.splitn(3, ' ');
+		
+		let method = parts.next().ok_or("method not found")?.trim().to_ascii_uppercase();
+		if !matches!(method.as_str(), "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD") {
+			return Err("unsupported method".into());
+		}
+		
+		let path = urlencoding::decode(parts.next().ok_or("url not found")?)?.to_string();
+		
+		let version = parts.next().ok_or("version not found")?;
+		if !matches!(version, "HTTP/1.0" | "HTTP/1.1") {
+			return Err("unsupported version".into());
+		}
+		
+		Ok((Some(method), Some(path), Some(String::from(version))))
+	}
+	
+	fn parse_header(as_bytes: &[u8]) -> Option<(String, String)> {
+		let header = std::str::from_utf8(as_bytes).ok()?;
+		let mut parts = header.splitn(2, ':');
+		
+		let (key, value) = (parts.next()?, parts.next()?);
+		Some((String::from(key.trim()), value.trim().replace(['\r', '\n'], "")))
+	}
+}
@@ -0,0 +1,116 @@
+/*
+ * Throughput and allocations per request of `HttpRequest::new` against the parser of
+ * the baseline tree, which copied the target, every field and the body into owned
+ * `String`s and `Vec`s. Filling the read buffer is left out for both.
+ *
+ * cargo bench --bench parser
+ *
+ * Its allocator counting allocations is the one of this target only, the library and
+ * its unit tests keep the system one.
+ */
+mod baseline_parser;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use rustrate::core::HttpRequest;
+use baseline_parser::BaselineRequest;
+
+const ITERATIONS: u32 = 200_000;
+
+const REQUESTS: &[(&str, &str)] = &[
+	("GET", "GET /api/v1/items?page=2&sort=name HTTP/1.1\r\nHost: example.com\r\nUser-Agent: Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0\r\nAccept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\nAccept-Language: en-US,en;q=0.5\r\nAccept-Encoding: gzip, deflate, br\r\nConnection: keep-alive\r\nCookie: session=3f2a9c; theme=dark\r\nCache-Control: no-cache\r\n\r\n"),
+	("POST", "POST /api/v1/items HTTP/1.1\r\nHost: example.com\r\nUser-Agent: curl/8.5.0\r\nAccept: */*\r\nContent-Type: application/json\r\nContent-Length: 46\r\n\r\n{\"name\":\"widget\",\"price\":12.5,\"tags\":[\"new\"]}"),
+];
+
+thread_local! {
+	static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Counts the allocations of the current thread, so threads started by the runtime
+/// do not skew it.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		count_allocation();
+		unsafe { System.alloc(layout) }
+	}
+	
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		unsafe { System.dealloc(ptr, layout) }
+	}
+	
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		count_allocation();
+		unsafe { System.realloc(ptr, layout, new_size) }
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocation() {
+	// The thread local is gone while the thread shuts down
+	let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+}
+
+fn allocations() -> u64 {
+	ALLOCATIONS.with(Cell::get)
+}
+
+struct Measurement {
+	elapsed: Duration,
+	allocations: u64,
+	bytes: usize,
+}
+
+impl Measurement {
+	fn report(&self, parser: &str, request: &str) {
+		let seconds = self.elapsed.as_secs_f64();
+		println!(
+			"{:<6} {:<8} {:>12.0} req/s {:>9.1} MB/s {:>6.1} allocations/req",
+			request,
+			parser,
+			ITERATIONS as f64 / seconds,
+			self.bytes as f64 * ITERATIONS as f64 / seconds / 1_000_000.0,
+			self.allocations as f64 / ITERATIONS as f64
+		);
+	}
+}
+
+async fn measure_borrowed(raw: &str) -> Measurement {
+	let buffer = Bytes::copy_from_slice(raw.as_bytes());
+	// First clone of a buffer made from a `Vec` allocates its shared counter
+	drop(buffer.clone());
+	
+	let allocations_before = allocations();
+	let start = Instant::now();
+	for _ in 0..ITERATIONS {
+		let req = HttpRequest::new(buffer.clone()).await.unwrap();
+		black_box((req.path(), req.headers.get("Host").map(str::len), req.body.len()));
+	}
+	
+	Measurement { elapsed: start.elapsed(), allocations: allocations() - allocations_before, bytes: raw.len() }
+}
+
+fn measure_baseline(raw: &str) -> Measurement {
+	let allocations_before = allocations();
+	let start = Instant::now();
+	for _ in 0..ITERATIONS {
+		let req = BaselineRequest::new(black_box(raw.as_bytes())).unwrap();
+		black_box((req.path, req.method, req.version, req.headers.get("Host").map(String::len), req.body.len()));
+	}
+	
+	Measurement { elapsed: start.elapsed(), allocations: allocations() - allocations_before, bytes: raw.len() }
+}
+
+fn main() {
+	let runtime = tokio::runtime::Builder::new_current_thread().build().expect("failed to start the runtime");
+	for (name, raw) in REQUESTS {
+		measure_baseline(raw).report("baseline", name);
+		runtime.block_on(measure_borrowed(raw)).report("borrowed", name);
+	}
+}
@@ -0,0 +1,135 @@
+use std::sync::{Arc, LazyLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::timeout;
+use crate::{BULKHEAD_QUEUE_TIMEOUT_MS, ROUTE_BULKHEADS};
+
+struct Compartment {
+	prefix: &'static str,
+	permits: Arc<Semaphore>,
+	max_in_flight: usize,
+	max_queued: usize,
+	queued: AtomicUsize,
+}
+
+impl Compartment {
+	fn new(prefix: &'static str, max_in_flight: usize, max_queued: usize) -> Self {
+		Self {
+			prefix,
+			permits: Arc::new(Semaphore::new(max_in_flight)),
+			max_in_flight,
+			max_queued,
+			queued: AtomicUsize::new(0),
+		}
+	}
+	
+	async fn acquire(&self, wait: Duration) -> Result<OwnedSemaphorePermit, ()> {
+		if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+			return Ok(permit);
+		}
+		
+		let queued = self.queued.fetch_add(1, Ordering::AcqRel);
+		if queued >= self.max_queued {
+			self.queued.fetch_sub(1, Ordering::AcqRel);
+			return Err(());
+		}
+		
+		let permit = timeout(wait, self.permits.clone().acquire_owned()).await;
+		self.queued.fetch_sub(1, Ordering::AcqRel);
+		
+		match permit {
+			Ok(Ok(permit)) => Ok(permit),
+			_ => Err(()),
+		}
+	}
+}
+
+static COMPARTMENTS: LazyLock<Vec<Compartment>> = LazyLock::new(|| {
+	ROUTE_BULKHEADS
+		.iter()
+		.map(|(prefix, max_in_flight, max_queued)| Compartment::new(prefix, *max_in_flight, *max_queued))
+		.collect()
+});
+
+/// Occupancy of a single route compartment.
+pub struct BulkheadOccupancy {
+	pub prefix: &'static str,
+	pub in_flight: usize,
+	pub max_in_flight: usize,
+	pub queued: usize,
+	pub max_queued: usize,
+}
+
+/// Caps the requests a route (see `ROUTE_BULKHEADS`) may run at once, so a slow
+/// endpoint can't take every task of the server with it.
+pub struct Bulkhead;
+
+impl Bulkhead {
+	/// Returns `Ok(None)` for routes without a compartment, `Err(())` when both the
+	/// compartment and its wait queue are full or the queue wait timed out.
+	pub async fn acquire(path: &str) -> Result<Option<OwnedSemaphorePermit>, ()> {
+		let Some(compartment) = COMPARTMENTS
+			.iter()
+			.filter(|compartment| path.starts_with(compartment.prefix))
+			.max_by_key(|compartment| compartment.prefix.len())
+		else {
+			return Ok(None);
+		};
+		
+		compartment.acquire(Duration::from_millis(BULKHEAD_QUEUE_TIMEOUT_MS)).await.map(Some)
+	}
+	
+	pub fn occupancy() -> Vec<BulkheadOccupancy> {
+		COMPARTMENTS
+			.iter()
+			.map(|compartment| BulkheadOccupancy {
+				prefix: compartment.prefix,
+				in_flight: compartment.max_in_flight - compartment.permits.available_permits(),
+				max_in_flight: compartment.max_in_flight,
+				queued: compartment.queued.load(Ordering::Acquire),
+				max_queued: compartment.max_queued,
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const WAIT: Duration = Duration::from_millis(50);
+	
+	#[tokio::test]
+	async fn lets_routes_without_a_compartment_through() {
+		assert!(matches!(Bulkhead::acquire("/unlisted").await, Ok(None)));
+	}
+	
+	#[tokio::test]
+	async fn gives_up_on_a_queue_wait_past_the_timeout() {
+		let compartment = Compartment::new("/slow", 1, 1);
+		let _held = compartment.acquire(WAIT).await.unwrap();
+		
+		assert!(compartment.acquire(WAIT).await.is_err());
+		assert_eq!(compartment.queued.load(Ordering::Acquire), 0);
+	}
+	
+	#[tokio::test]
+	async fn rejects_past_a_full_queue_and_serves_the_queued_request_next() {
+		let compartment = Arc::new(Compartment::new("/slow", 1, 1));
+		let held = compartment.acquire(WAIT).await.unwrap();
+		
+		let waiting = {
+			let compartment = compartment.clone();
+			tokio::spawn(async move { compartment.acquire(Duration::from_secs(5)).await.is_ok() })
+		};
+		while compartment.queued.load(Ordering::Acquire) == 0 {
+			tokio::task::yield_now().await;
+		}
+		
+		assert!(compartment.acquire(Duration::from_secs(5)).await.is_err());
+		
+		drop(held);
+		assert!(waiting.await.unwrap());
+	}
+}
@@ -0,0 +1,225 @@
+use std::io::{Error as IoError, ErrorKind};
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use crate::core::HeaderMap;
+use crate::utils::helper::{decode_hex, encode_hex, random_bytes};
+use crate::{POW_CHALLENGE_TTL_SECS, POW_DIFFICULTY_BITS, POW_PASS_TTL_SECS, POW_VERIFY_PATH};
+
+const PASS_COOKIE: &str = "rustrate_pass";
+
+static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Proof-of-work interstitial for clients past the soft rate limit.
+///
+/// The page asks the browser for a nonce such that `SHA-256("<challenge>:<nonce>")`
+/// starts with `POW_DIFFICULTY_BITS` zero bits. Challenges and passes are both
+/// HMAC-signed and bound to the client IP, so the server keeps no state for them.
+///
+/// Without that state a solution is not spent once used: until the challenge expires,
+/// after `POW_CHALLENGE_TTL_SECS`, the same client IP can present it again for a new pass.
+pub struct Challenge;
+
+impl Challenge {
+	/// Loads the signing secret. Signing with an empty key or one that failed to generate
+	/// would make every challenge and pass forgeable, so the error has to stop the startup.
+	pub fn init() -> Result<(), IoError> {
+		let secret = match std::env::var("RUSTRATE_POW_SECRET") {
+			Ok(secret) if secret.is_empty() => return Err(IoError::new(ErrorKind::InvalidInput, "RUSTRATE_POW_SECRET must not be empty")),
+			Ok(secret) => secret.into_bytes(),
+			// Without a shared secret, passes only hold for this instance and this run
+			Err(_) => random_bytes(32)?,
+		};
+		
+		if SECRET.set(secret).is_err() {
+			eprintln!("Proof-of-work secret is already initialized");
+		}
+		
+		Ok(())
+	}
+	
+	pub fn page(ip: IpAddr) -> String {
+		let issued = now();
+		let challenge = format!("{}.{}", issued, sign(&format!("challenge|{}|{}", ip, issued)));
+		
+		format!(
+			r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Checking your browser</title></head>
+<body>
+<p>Checking your browser, this takes a few seconds&hellip;</p>
+<script>
+(async () => {{
+	const challenge = "{challenge}";
+	const difficulty = {difficulty};
+	const encoder = new TextEncoder();
+	for (let nonce = 0; ; nonce++) {{
+		const digest = new Uint8Array(await crypto.subtle.digest("SHA-256", encoder.encode(challenge + ":" + nonce)));
+		let bits = 0;
+		for (const byte of digest) {{
+			if (byte === 0) {{ bits += 8; continue; }}
+			bits += Math.clz32(byte) - 24;
+			break;
+		}}
+		if (bits >= difficulty) {{
+			const res = await fetch("{verify_path}?challenge=" + encodeURIComponent(challenge) + "&nonce=" + nonce);
+			if (res.ok) location.reload();
+			return;
+		}}
+	}}
+}})();
+</script>
+</body>
+</html>"#,
+			challenge = challenge,
+			difficulty = POW_DIFFICULTY_BITS,
+			verify_path = POW_VERIFY_PATH
+		)
+	}
+	
+	/// Checks a solved challenge and returns the `Set-Cookie` value of the pass it earns.
+	/// A solution verifies as often as it is sent until its challenge expires.
+	pub fn verify(ip: IpAddr, challenge: &str, nonce: &str) -> Option<String> {
+		let (issued, signature) = challenge.split_once('.')?;
+		let issued: u64 = issued.parse().ok()?;
+		if now().saturating_sub(issued) > POW_CHALLENGE_TTL_SECS || !verify_signature(&format!("challenge|{}|{}", ip, issued), signature) {
+			return None;
+		}
+		
+		let digest = Sha256::digest(format!("{}:{}", challenge, nonce).as_bytes());
+		if leading_zero_bits(&digest) < POW_DIFFICULTY_BITS as u32 {
+			return None;
+		}
+		
+		let expires = now() + POW_PASS_TTL_SECS;
+		let pass = format!("{}.{}", expires, sign(&format!("pass|{}|{}", ip, expires)));
+		
+		Some(format!("{}={}; Max-Age={}; Path=/; HttpOnly; SameSite=Lax", PASS_COOKIE, pass, POW_PASS_TTL_SECS))
+	}
+	
+	/// Whether the `Cookie` headers carry a valid, unexpired pass for `ip`.
+	pub fn has_pass(ip: IpAddr, headers: &HeaderMap) -> bool {
+		headers
+			.get_all("Cookie")
+			.flat_map(|cookie_header| cookie_header.split(';'))
+			.filter_map(|cookie| cookie.trim().split_once('='))
+			.filter(|(name, _)| *name == PASS_COOKIE)
+			.any(|(_, pass)| {
+				let Some((expires, signature)) = pass.split_once('.') else {
+					return false;
+				};
+				
+				matches!(expires.parse::<u64>(), Ok(expires) if expires > now() && verify_signature(&format!("pass|{}|{}", ip, expires), signature))
+			})
+	}
+}
+
+fn secret() -> &'static [u8] {
+	SECRET.get().expect("Challenge::init runs at startup")
+}
+
+fn sign(message: &str) -> String {
+	let mut mac = Hmac::<Sha256>::new_from_slice(secret()).expect("HMAC accepts keys of any size");
+	mac.update(message.as_bytes());
+	
+	encode_hex(&mac.finalize().into_bytes())
+}
+
+fn verify_signature(message: &str, signature: &str) -> bool {
+	let Some(signature) = decode_hex(signature) else {
+		return false;
+	};
+	
+	let mut mac = Hmac::<Sha256>::new_from_slice(secret()).expect("HMAC accepts keys of any size");
+	mac.update(message.as_bytes());
+	
+	mac.verify_slice(&signature).is_ok()
+}
+
+fn leading_zero_bits(digest: &[u8]) -> u32 {
+	let mut bits: u32 = 0;
+	for byte in digest {
+		bits += byte.leading_zeros();
+		if *byte != 0 {
+			break;
+		}
+	}
+	
+	bits
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn challenge(ip: IpAddr, issued: u64) -> String {
+		Challenge::init().unwrap();
+		format!("{}.{}", issued, sign(&format!("challenge|{}|{}", ip, issued)))
+	}
+	
+	fn solve(challenge: &str) -> String {
+		(0u64..)
+			.map(|nonce| nonce.to_string())
+			.find(|nonce| leading_zero_bits(&Sha256::digest(format!("{}:{}", challenge, nonce).as_bytes())) >= POW_DIFFICULTY_BITS as u32)
+			.unwrap()
+	}
+	
+	fn cookie_header(set_cookie: &str) -> HeaderMap {
+		let cookie = set_cookie.split(';').next().unwrap();
+		HeaderMap::from([("Cookie", format!("theme=dark; {}", cookie))])
+	}
+	
+	#[test]
+	fn binds_passes_to_the_ip() {
+		let ip: IpAddr = "192.0.2.1".parse().unwrap();
+		let challenge = challenge(ip, now());
+		let nonce = solve(&challenge);
+		
+		assert!(Challenge::verify("192.0.2.2".parse().unwrap(), &challenge, &nonce).is_none());
+		
+		let set_cookie = Challenge::verify(ip, &challenge, &nonce).unwrap();
+		assert!(Challenge::has_pass(ip, &cookie_header(&set_cookie)));
+		assert!(!Challenge::has_pass("192.0.2.2".parse().unwrap(), &cookie_header(&set_cookie)));
+	}
+	
+	#[test]
+	fn accepts_a_solution_until_its_challenge_expires() {
+		let ip: IpAddr = "192.0.2.1".parse().unwrap();
+		let challenge = challenge(ip, now());
+		let nonce = solve(&challenge);
+		
+		assert!(Challenge::verify(ip, &challenge, &nonce).is_some());
+		assert!(Challenge::verify(ip, &challenge, &nonce).is_some());
+	}
+	
+	#[test]
+	fn rejects_expired_challenges_and_passes() {
+		let ip: IpAddr = "192.0.2.1".parse().unwrap();
+		let stale = challenge(ip, now() - POW_CHALLENGE_TTL_SECS - 1);
+		assert!(Challenge::verify(ip, &stale, &solve(&stale)).is_none());
+		
+		let pass = |expires: u64| format!("{}={}.{}", PASS_COOKIE, expires, sign(&format!("pass|{}|{}", ip, expires)));
+		assert!(Challenge::has_pass(ip, &cookie_header(&pass(now() + 60))));
+		assert!(!Challenge::has_pass(ip, &cookie_header(&pass(now() - 1))));
+	}
+	
+	#[test]
+	fn rejects_forged_passes() {
+		let ip: IpAddr = "192.0.2.1".parse().unwrap();
+		Challenge::init().unwrap();
+		
+		let expires = now() + 60;
+		let signature = sign(&format!("pass|{}|{}", ip, expires));
+		
+		assert!(!Challenge::has_pass(ip, &cookie_header(&format!("{}={}.{}", PASS_COOKIE, expires + 1, signature))));
+		assert!(!Challenge::has_pass(ip, &cookie_header(&format!("{}={}.{}", PASS_COOKIE, expires, &signature[2..]))));
+		assert!(!Challenge::has_pass(ip, &cookie_header(&format!("{}={}", PASS_COOKIE, expires))));
+	}
+}
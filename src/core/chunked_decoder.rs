@@ -0,0 +1,215 @@
+use bytes::{Buf, Bytes, BytesMut};
+use crate::core::{HeaderMap, HttpRequest};
+use crate::enums::HttpError;
+use crate::{MAX_HEADERS_LENGTH, MAX_HEADERS_SIZE};
+
+// Longest chunk size line, extensions included
+const MAX_CHUNK_LINE_LENGTH: usize = 1024;
+
+/*
+ * Fields a sender may not put in trailers (RFC 9110 section 6.5.1): framing, routing,
+ * authentication and anything the request was already acted on by. A trailer cannot
+ * override what the head said, so these are dropped.
+ */
+const FORBIDDEN_TRAILERS: &[&str] = &[
+	"Authorization",
+	"Cache-Control",
+	"Connection",
+	"Content-Encoding",
+	"Content-Length",
+	"Content-Range",
+	"Content-Type",
+	"Cookie",
+	"Expect",
+	"Host",
+	"Proxy-Authorization",
+	"TE",
+	"Trailer",
+	"Transfer-Encoding",
+];
+
+enum State {
+	Size,
+	Data(usize),
+	DataEnd,
+	Trailers(usize),
+	Done,
+}
+
+/// Incremental decoder of `Transfer-Encoding: chunked` bodies (RFC 9112 section 7.1).
+///
+/// Fed with whatever the socket delivered, it consumes complete elements from the buffer
+/// and leaves the rest there, including anything past the end of the body. Trailer fields
+/// are parsed like header fields and kept apart from them.
+pub struct ChunkedDecoder {
+	state: State,
+	body: BytesMut,
+	trailers: HeaderMap,
+	trailer_count: usize,
+	max_size: usize,
+}
+
+impl ChunkedDecoder {
+	pub fn new(max_size: usize) -> Self {
+		Self { state: State::Size, body: BytesMut::new(), trailers: HeaderMap::new(), trailer_count: 0, max_size }
+	}
+	
+	/// Consumes what it can from `buffer`, `true` once the body is complete.
+	pub fn decode(&mut self, buffer: &mut BytesMut) -> Result<bool, HttpError> {
+		loop {
+			match self.state {
+				State::Size => {
+					let Some(line) = take_line(buffer, MAX_CHUNK_LINE_LENGTH)? else {
+						return Ok(false);
+					};
+					
+					let size = parse_chunk_size(&line)?;
+					if size > self.max_size - self.body.len() {
+						return Err(HttpError::BodyTooLarge);
+					}
+					
+					self.state = if size == 0 { State::Trailers(0) } else { State::Data(size) };
+				},
+				State::Data(remaining) => {
+					if buffer.is_empty() {
+						return Ok(false);
+					}
+					
+					let n = remaining.min(buffer.len());
+					self.body.extend_from_slice(&buffer.split_to(n));
+					self.state = if n == remaining { State::DataEnd } else { State::Data(remaining - n) };
+				},
+				State::DataEnd => {
+					if buffer.len() < 2 {
+						return Ok(false);
+					}
+					
+					if &buffer[..2] != b"\r\n" {
+						return Err(HttpError::InvalidChunk);
+					}
+					
+					buffer.advance(2);
+					self.state = State::Size;
+				},
+				State::Trailers(size) => {
+					let Some(line) = take_line(buffer, MAX_HEADERS_SIZE.saturating_sub(size))? else {
+						return Ok(false);
+					};
+					
+					if line.is_empty() {
+						self.state = State::Done;
+						continue;
+					}
+					
+					self.add_trailer(&line)?;
+					self.state = State::Trailers(size + line.len() + 2);
+				},
+				State::Done => return Ok(true),
+			}
+		}
+	}
+	
+	/// Whether `name` may be sent as a trailer field, in a request or a response.
+	pub fn allows_trailer(name: &[u8]) -> bool {
+		!FORBIDDEN_TRAILERS.iter().any(|forbidden| forbidden.as_bytes().eq_ignore_ascii_case(name))
+	}
+	
+	/// The decoded body and its trailer fields.
+	pub fn into_parts(self) -> (Bytes, HeaderMap) {
+		(self.body.freeze(), self.trailers)
+	}
+	
+	fn add_trailer(&mut self, line: &Bytes) -> Result<(), HttpError> {
+		self.trailer_count += 1;
+		if self.trailer_count > MAX_HEADERS_LENGTH {
+			return Err(HttpError::HeadersTooLarge);
+		}
+		
+		let (name, value) = HttpRequest::parse_header(line)?;
+		if Self::allows_trailer(name) {
+			self.trailers.append_slices(line.slice_ref(name), line.slice_ref(value));
+		}
+		
+		Ok(())
+	}
+}
+
+/// Next CRLF-terminated line of `buffer` without its CRLF, `None` while incomplete.
+fn take_line(buffer: &mut BytesMut, max_length: usize) -> Result<Option<Bytes>, HttpError> {
+	let Some(end) = buffer.windows(2).position(|window| window == b"\r\n") else {
+		if buffer.len() > max_length {
+			return Err(HttpError::InvalidChunk);
+		}
+		
+		return Ok(None);
+	};
+	
+	if end > max_length || buffer[..end].contains(&b'\n') {
+		return Err(HttpError::InvalidChunk);
+	}
+	
+	let line = buffer.split_to(end).freeze();
+	buffer.advance(2);
+	Ok(Some(line))
+}
+
+/*
+ * chunk-size [ chunk-ext ], chunk-size = 1*HEXDIG
+ * Extensions carry nothing this server understands, they are skipped.
+ */
+fn parse_chunk_size(line: &[u8]) -> Result<usize, HttpError> {
+	let size = line.split(|byte| *byte == b';').next().unwrap_or_default().trim_ascii_end();
+	if size.is_empty() || size.len() > 16 || !size.iter().all(u8::is_ascii_hexdigit) {
+		return Err(HttpError::InvalidChunk);
+	}
+	
+	std::str::from_utf8(size).ok().and_then(|size| usize::from_str_radix(size, 16).ok()).ok_or(HttpError::InvalidChunk)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn decodes_bodies_across_reads() {
+		let mut decoder = ChunkedDecoder::new(16);
+		let mut buffer = BytesMut::from(&b"4;ext=1\r\nWiki\r\n7\r\npedia"[..]);
+		assert_eq!(decoder.decode(&mut buffer), Ok(false));
+		
+		buffer.extend_from_slice(b" i\r\n0\r\nX-Trailer: 1\r\n\r\nGET");
+		assert_eq!(decoder.decode(&mut buffer), Ok(true));
+		assert_eq!(&buffer[..], b"GET");
+		
+		let (body, trailers) = decoder.into_parts();
+		assert_eq!(body, &b"Wikipedia i"[..]);
+		assert_eq!(trailers.get("x-trailer"), Some("1"));
+	}
+	
+	#[test]
+	fn drops_forbidden_trailers() {
+		let mut decoder = ChunkedDecoder::new(16);
+		assert_eq!(decoder.decode(&mut BytesMut::from(&b"0\r\nContent-Length: 5\r\nDigest: sha-256=x\r\nte: trailers\r\n\r\n"[..])), Ok(true));
+		assert_eq!(decoder.into_parts().1.iter().collect::<Vec<(&str, &str)>>(), [("Digest", "sha-256=x")]);
+		
+		assert!(ChunkedDecoder::allows_trailer(b"Server-Timing"));
+		assert!(!ChunkedDecoder::allows_trailer(b"transfer-encoding"));
+	}
+	
+	#[test]
+	fn rejects_malformed_chunks() {
+		let rejected: [(&[u8], HttpError); 5] = [
+			(b"11\r\n", HttpError::BodyTooLarge),
+			(b"4\r\nWikiX\r\n", HttpError::InvalidChunk),
+			(b"-1\r\n", HttpError::InvalidChunk),
+			(b"4\nWiki\r\n", HttpError::InvalidChunk),
+			(b"0\r\nX-Trailer 1\r\n\r\n", HttpError::HeaderWithoutColon),
+		];
+		for (raw, error) in rejected {
+			assert_eq!(ChunkedDecoder::new(16).decode(&mut BytesMut::from(raw)), Err(error), "{:?}", String::from_utf8_lossy(raw));
+		}
+		
+		let too_many = format!("0\r\n{}\r\n", "X-Trailer: 1\r\n".repeat(MAX_HEADERS_LENGTH + 1));
+		assert_eq!(ChunkedDecoder::new(16).decode(&mut BytesMut::from(too_many.as_bytes())), Err(HttpError::HeadersTooLarge));
+	}
+}
@@ -0,0 +1,11 @@
+use std::net::SocketAddr;
+use crate::core::GeoInfo;
+
+/// What is known about the peer of a connection once it has been accepted.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+	pub addr: SocketAddr,
+	pub geo: GeoInfo,
+	/// JA4-style fingerprint of the ClientHello, for TLS connections only.
+	pub tls_fingerprint: Option<String>,
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{LazyLock, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::sync::Notify;
+use crate::enums::ConnectionOverflow;
+use crate::{
+	MAX_CONNECTIONS,
+	MAX_CONNECTIONS_PER_CLIENT,
+	CONNECTION_LIMIT_IPV4_PREFIX,
+	CONNECTION_LIMIT_IPV6_PREFIX
+};
+
+static CONNECTIONS: LazyLock<Connections> = LazyLock::new(|| Connections::new(MAX_CONNECTIONS, MAX_CONNECTIONS_PER_CLIENT));
+static REJECTED: AtomicU64 = AtomicU64::new(0);
+static RELEASED: Notify = Notify::const_new();
+
+/// Open connections, in total and per client prefix, against their limits.
+struct Connections {
+	open: AtomicUsize,
+	per_client: Mutex<HashMap<IpAddr, usize>>,
+	max_connections: usize,
+	max_per_client: usize,
+}
+
+impl Connections {
+	fn new(max_connections: usize, max_per_client: usize) -> Self {
+		Self {
+			open: AtomicUsize::new(0),
+			per_client: Mutex::new(HashMap::new()),
+			max_connections,
+			max_per_client,
+		}
+	}
+	
+	fn try_acquire(&'static self, ip: IpAddr) -> Option<ConnectionGuard> {
+		let client = ConnectionLimiter::client_prefix(ip);
+		let Ok(mut per_client) = self.per_client.lock() else {
+			return None;
+		};
+		
+		let count = per_client.get(&client).copied().unwrap_or(0);
+		if count >= self.max_per_client || self.open.load(Ordering::Acquire) >= self.max_connections {
+			REJECTED.fetch_add(1, Ordering::Relaxed);
+			return None;
+		}
+		
+		per_client.insert(client, count + 1);
+		self.open.fetch_add(1, Ordering::AcqRel);
+		
+		Some(ConnectionGuard { connections: self, client })
+	}
+	
+	/// Whether to accept at all: past the global limit, `Backlog` leaves new connections
+	/// to the kernel while `Close` accepts them only to close them.
+	fn is_accepting(&self, overflow: ConnectionOverflow) -> bool {
+		overflow == ConnectionOverflow::Close || self.open.load(Ordering::Acquire) < self.max_connections
+	}
+	
+	fn release(&self, client: IpAddr) {
+		if let Ok(mut per_client) = self.per_client.lock() && let Some(count) = per_client.get_mut(&client) {
+			*count = count.saturating_sub(1);
+			if *count == 0 {
+				per_client.remove(&client);
+			}
+		}
+		
+		self.open.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
+/// Bounds the number of concurrently open connections, globally and per client
+/// prefix (`CONNECTION_LIMIT_IPV4_PREFIX` / `CONNECTION_LIMIT_IPV6_PREFIX`).
+pub struct ConnectionLimiter;
+
+/// Keeps its connection counted until dropped.
+pub struct ConnectionGuard {
+	connections: &'static Connections,
+	client: IpAddr,
+}
+
+impl ConnectionLimiter {
+	pub fn try_acquire(ip: IpAddr) -> Option<ConnectionGuard> {
+		CONNECTIONS.try_acquire(ip)
+	}
+	
+	pub fn is_accepting(overflow: ConnectionOverflow) -> bool {
+		CONNECTIONS.is_accepting(overflow)
+	}
+	
+	/// Resolves once a connection has been closed.
+	pub async fn released() {
+		RELEASED.notified().await;
+	}
+	
+	pub fn open() -> usize {
+		CONNECTIONS.open.load(Ordering::Acquire)
+	}
+	
+	pub fn rejected() -> u64 {
+		REJECTED.load(Ordering::Relaxed)
+	}
+	
+	pub fn clients() -> usize {
+		CONNECTIONS.per_client.lock().map(|per_client| per_client.len()).unwrap_or(0)
+	}
+	
+	fn client_prefix(ip: IpAddr) -> IpAddr {
+		match ip {
+			IpAddr::V4(v4) => {
+				let mask = u32::MAX.checked_shl(32 - CONNECTION_LIMIT_IPV4_PREFIX.min(32)).unwrap_or(0);
+				IpAddr::V4((u32::from(v4) & mask).into())
+			},
+			IpAddr::V6(v6) => {
+				let mask = u128::MAX.checked_shl(128 - CONNECTION_LIMIT_IPV6_PREFIX.min(128)).unwrap_or(0);
+				IpAddr::V6((u128::from(v6) & mask).into())
+			}
+		}
+	}
+}
+
+impl Drop for ConnectionGuard {
+	fn drop(&mut self) {
+		self.connections.release(self.client);
+		RELEASED.notify_one();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn connections(max_connections: usize, max_per_client: usize) -> &'static Connections {
+		Box::leak(Box::new(Connections::new(max_connections, max_per_client)))
+	}
+	
+	fn ip(ip: &str) -> IpAddr {
+		ip.parse().unwrap()
+	}
+	
+	#[test]
+	fn limits_each_client_prefix() {
+		let connections = connections(10, 2);
+		
+		let _first = connections.try_acquire(ip("2001:db8::1")).unwrap();
+		let second = connections.try_acquire(ip("2001:db8::ffff")).unwrap();
+		assert!(connections.try_acquire(ip("2001:db8::2")).is_none(), "same /{} as the others", CONNECTION_LIMIT_IPV6_PREFIX);
+		assert!(connections.try_acquire(ip("2001:db8:0:1::1")).is_some());
+		assert!(connections.try_acquire(ip("10.0.0.1")).is_some());
+		
+		drop(second);
+		assert!(connections.try_acquire(ip("2001:db8::2")).is_some());
+	}
+	
+	#[test]
+	fn closes_past_the_global_limit() {
+		let connections = connections(2, 2);
+		
+		let first = connections.try_acquire(ip("10.0.0.1")).unwrap();
+		let _second = connections.try_acquire(ip("10.0.0.2")).unwrap();
+		assert!(connections.is_accepting(ConnectionOverflow::Close));
+		assert!(connections.try_acquire(ip("10.0.0.3")).is_none());
+		
+		drop(first);
+		assert!(connections.try_acquire(ip("10.0.0.3")).is_some());
+	}
+	
+	#[test]
+	fn stops_accepting_past_the_global_limit_in_backlog_mode() {
+		let connections = connections(2, 1);
+		
+		let first = connections.try_acquire(ip("10.0.0.1")).unwrap();
+		assert!(connections.is_accepting(ConnectionOverflow::Backlog));
+		
+		// The per-client limit still closes the connections it refuses
+		assert!(connections.try_acquire(ip("10.0.0.1")).is_none());
+		
+		let _second = connections.try_acquire(ip("10.0.0.2")).unwrap();
+		assert!(!connections.is_accepting(ConnectionOverflow::Backlog));
+		
+		drop(first);
+		assert!(connections.is_accepting(ConnectionOverflow::Backlog));
+		assert_eq!(connections.open.load(Ordering::Acquire), 1);
+	}
+}
@@ -0,0 +1,146 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::net::IpAddr;
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, SystemTime};
+use maxminddb::{geoip2, Reader};
+use tokio::{fs, time::interval};
+use crate::enums::GeoPolicy;
+use crate::{GEOIP_RELOAD_INTERVAL_SECS, GEO_POLICIES};
+
+/// Location of a client, as far as the loaded databases know it.
+#[derive(Debug, Clone, Default)]
+pub struct GeoInfo {
+	pub country: Option<String>,
+	pub asn: Option<u32>,
+	pub as_organization: Option<String>,
+}
+
+impl Display for GeoInfo {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "country {}", self.country.as_deref().unwrap_or("unknown"))?;
+		match (self.asn, &self.as_organization) {
+			(Some(asn), Some(organization)) => write!(f, ", AS{} {}", asn, organization),
+			(Some(asn), None) => write!(f, ", AS{}", asn),
+			_ => write!(f, ", AS unknown"),
+		}
+	}
+}
+
+struct Databases {
+	country: Option<Reader<Vec<u8>>>,
+	asn: Option<Reader<Vec<u8>>>,
+}
+
+static DATABASES: LazyLock<RwLock<Databases>> = LazyLock::new(|| RwLock::new(Databases { country: None, asn: None }));
+
+/// Resolves clients against local MaxMind databases (GeoLite2/GeoIP2 Country or City,
+/// and ASN) and evaluates `GEO_POLICIES` for them.
+pub struct GeoIp;
+
+impl GeoIp {
+	pub fn lookup(ip: IpAddr) -> GeoInfo {
+		let mut info = GeoInfo::default();
+		let Ok(databases) = DATABASES.read() else {
+			return info;
+		};
+		
+		if let Some(reader) = &databases.country && let Ok(record) = reader.lookup::<geoip2::Country>(ip) {
+			info.country = record.country.and_then(|country| country.iso_code).map(String::from);
+		}
+		
+		if let Some(reader) = &databases.asn && let Ok(record) = reader.lookup::<geoip2::Asn>(ip) {
+			info.asn = record.autonomous_system_number;
+			info.as_organization = record.autonomous_system_organization.map(String::from);
+		}
+		
+		info
+	}
+	
+	pub fn is_denied(info: &GeoInfo) -> bool {
+		GEO_POLICIES.iter().any(|policy| match policy {
+			GeoPolicy::DenyCountry(country) => info.country.as_deref() == Some(*country),
+			GeoPolicy::DenyAsn(asn) => info.asn == Some(*asn),
+			_ => false,
+		})
+	}
+	
+	/// Rate limit keys and budgets of the policies matching `info`.
+	pub fn rate_limits(info: &GeoInfo) -> Vec<(String, u8)> {
+		GEO_POLICIES
+			.iter()
+			.filter_map(|policy| match policy {
+				GeoPolicy::RateLimitCountry(country, limit) if info.country.as_deref() == Some(*country) => {
+					Some((format!("country:{}", country), *limit))
+				},
+				GeoPolicy::RateLimitAsn(asn, limit) if info.asn == Some(*asn) => {
+					Some((format!("asn:{}", asn), *limit))
+				},
+				_ => None,
+			})
+			.collect()
+	}
+	
+	/// Loads the databases and reloads them whenever their files change on disk.
+	pub async fn watch(country_path: Option<String>, asn_path: Option<String>) {
+		let mut ticker = interval(Duration::from_secs(GEOIP_RELOAD_INTERVAL_SECS));
+		let mut country_modified: Option<SystemTime> = None;
+		let mut asn_modified: Option<SystemTime> = None;
+		loop {
+			ticker.tick().await;
+			swap_databases(country_path.as_deref(), asn_path.as_deref(), &mut country_modified, &mut asn_modified).await;
+		}
+	}
+	
+	/// Loads the databases once, for commands that do not keep running.
+	pub async fn load(country_path: Option<&str>, asn_path: Option<&str>) {
+		swap_databases(country_path, asn_path, &mut None, &mut None).await;
+	}
+}
+
+async fn swap_databases(country_path: Option<&str>, asn_path: Option<&str>, country_modified: &mut Option<SystemTime>, asn_modified: &mut Option<SystemTime>) {
+	if let Some(reader) = reload(country_path, country_modified).await
+		&& let Ok(mut databases) = DATABASES.write() {
+		databases.country = Some(reader);
+	}
+	
+	if let Some(reader) = reload(asn_path, asn_modified).await
+		&& let Ok(mut databases) = DATABASES.write() {
+		databases.asn = Some(reader);
+	}
+}
+
+async fn reload(path: Option<&str>, last_modified: &mut Option<SystemTime>) -> Option<Reader<Vec<u8>>> {
+	let path = path?;
+	let modified = match fs::metadata(path).await.and_then(|metadata| metadata.modified()) {
+		Ok(modified) => modified,
+		Err(e) => {
+			eprintln!("Failed to read GeoIP database {}: {}", path, e);
+			return None;
+		}
+	};
+	
+	if *last_modified == Some(modified) {
+		return None;
+	}
+	
+	*last_modified = Some(modified);
+	
+	let content = match fs::read(path).await {
+		Ok(content) => content,
+		Err(e) => {
+			eprintln!("Failed to read GeoIP database {}: {}", path, e);
+			return None;
+		}
+	};
+	
+	match Reader::from_source(content) {
+		Ok(reader) => {
+			println!("Loaded GeoIP database {} ({})", path, reader.metadata.database_type);
+			Some(reader)
+		},
+		Err(e) => {
+			eprintln!("Failed to load GeoIP database {}: {}", path, e);
+			None
+		}
+	}
+}
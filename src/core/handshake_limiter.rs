@@ -0,0 +1,53 @@
+use std::net::IpAddr;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::time::interval;
+use crate::stores::MemoryStore;
+use crate::MAX_TLS_HANDSHAKES_PER_MINUTE;
+
+static HANDSHAKES: LazyLock<MemoryStore> = LazyLock::new(MemoryStore::new);
+
+/// Per-IP budget of TLS handshakes, checked right after accept so that a handshake
+/// flood is turned away before any crypto work is done for it.
+pub struct HandshakeLimiter;
+
+impl HandshakeLimiter {
+	pub fn allow(ip: IpAddr) -> bool {
+		Self::allow_at(&HANDSHAKES, ip, Instant::now())
+	}
+	
+	fn allow_at(handshakes: &MemoryStore, ip: IpAddr, now: Instant) -> bool {
+		handshakes.add_at(&ip.to_string(), 1, now) <= MAX_TLS_HANDSHAKES_PER_MINUTE
+	}
+	
+	pub async fn cleanup() {
+		let mut ticker = interval(Duration::from_secs(5));
+		loop {
+			ticker.tick().await;
+			HANDSHAKES.cleanup_sync();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::RATE_LIMIT_WINDOW_SECS;
+	
+	#[test]
+	fn refuses_handshakes_past_the_budget_until_it_expires() {
+		let handshakes = MemoryStore::new();
+		let ip: IpAddr = "10.0.0.1".parse().unwrap();
+		let start = Instant::now();
+		
+		for _ in 0..MAX_TLS_HANDSHAKES_PER_MINUTE {
+			assert!(HandshakeLimiter::allow_at(&handshakes, ip, start));
+		}
+		
+		assert!(!HandshakeLimiter::allow_at(&handshakes, ip, start));
+		assert!(HandshakeLimiter::allow_at(&handshakes, "10.0.0.2".parse().unwrap(), start));
+		
+		let expired = start + Duration::from_secs(RATE_LIMIT_WINDOW_SECS);
+		assert!(HandshakeLimiter::allow_at(&handshakes, ip, expired));
+	}
+}
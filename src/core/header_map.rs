@@ -0,0 +1,144 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use bytes::Bytes;
+use crate::utils::helper::sanitize_header_value;
+
+/// HTTP header fields of a request or a response.
+///
+/// Names are matched case-insensitively but kept with their original casing, a name
+/// may carry several values, and fields are kept in the order they were added, which
+/// is also the order they are written on the wire.
+///
+/// Fields parsed from a request are slices of its read buffer rather than copies. Every
+/// name and value is valid UTF-8, which the constructors make sure of.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderMap {
+	entries: Vec<(Bytes, Bytes)>,
+}
+
+impl HeaderMap {
+	pub fn new() -> Self {
+		Self { entries: Vec::new() }
+	}
+	
+	/// Adds a value for `name`, after the ones it already has.
+	pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+		self.entries.push((Bytes::from(name.into()), Bytes::from(value.into())));
+	}
+	
+	/// Adds a field borrowed from a request buffer, a lossy copy when it is not UTF-8.
+	pub(crate) fn append_slices(&mut self, name: Bytes, value: Bytes) {
+		let to_utf8 = |bytes: Bytes| match std::str::from_utf8(&bytes) {
+			Ok(_) => bytes,
+			Err(_) => Bytes::from(String::from_utf8_lossy(&bytes).into_owned()),
+		};
+		
+		self.entries.push((to_utf8(name), to_utf8(value)));
+	}
+	
+	/// First value of `name`.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.entries.iter().find(|(key, _)| key.eq_ignore_ascii_case(name.as_bytes())).map(|(_, value)| as_str(value))
+	}
+	
+	/// Every value of `name`, in order.
+	pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.entries.iter().filter(move |(key, _)| key.eq_ignore_ascii_case(name.as_bytes())).map(|(_, value)| as_str(value))
+	}
+	
+	/// Every value of `name` as the buffer slices they are stored in.
+	pub(crate) fn get_all_bytes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Bytes> + 'a {
+		self.entries.iter().filter(move |(key, _)| key.eq_ignore_ascii_case(name.as_bytes())).map(|(_, value)| value)
+	}
+	
+	/// Drops every value of `name`, returning whether there was one.
+	pub fn remove(&mut self, name: &str) -> bool {
+		let before = self.entries.len();
+		self.entries.retain(|(key, _)| !key.eq_ignore_ascii_case(name.as_bytes()));
+		self.entries.len() != before
+	}
+	
+	pub fn contains_key(&self, name: &str) -> bool {
+		self.get(name).is_some()
+	}
+	
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.entries.iter().map(|(name, value)| (as_str(name), as_str(value)))
+	}
+}
+
+impl<const N: usize> From<[(&str, String); N]> for HeaderMap {
+	fn from(fields: [(&str, String); N]) -> Self {
+		let mut headers = Self::new();
+		for (name, value) in fields {
+			headers.append(name, value);
+		}
+		
+		headers
+	}
+}
+
+/// Wire format, one `Name: value\r\n` line per value.
+impl Display for HeaderMap {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for (name, value) in self.iter() {
+			write!(f, "{}: {}\r\n", name, sanitize_header_value(value))?;
+		}
+		
+		Ok(())
+	}
+}
+
+fn as_str(bytes: &Bytes) -> &str {
+	std::str::from_utf8(bytes).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn headers() -> HeaderMap {
+		let mut headers = HeaderMap::new();
+		headers.append("Accept", "text/html");
+		headers.append("X-Forwarded-For", "10.0.0.1");
+		headers.append("accept", "application/json");
+		headers
+	}
+	
+	#[test]
+	fn matches_names_case_insensitively() {
+		let headers = headers();
+		
+		assert_eq!(headers.get("ACCEPT"), Some("text/html"));
+		assert_eq!(headers.get_all("Accept").collect::<Vec<_>>(), ["text/html", "application/json"]);
+		assert!(headers.contains_key("x-forwarded-for"));
+		assert_eq!(headers.get("Host"), None);
+		assert_eq!(headers.get_all("Host").count(), 0);
+	}
+	
+	#[test]
+	fn removes_every_value_of_a_name() {
+		let mut headers = headers();
+		
+		assert!(headers.remove("ACCEPT"));
+		assert!(!headers.remove("accept"));
+		assert_eq!(headers.get("Accept"), None);
+		assert_eq!(headers.iter().collect::<Vec<_>>(), [("X-Forwarded-For", "10.0.0.1")]);
+	}
+	
+	#[test]
+	fn writes_the_wire_format_in_order() {
+		let mut headers = headers();
+		headers.append("X-Injected", "a\r\nSet-Cookie: b");
+		
+		assert_eq!(headers.to_string(), "Accept: text/html\r\nX-Forwarded-For: 10.0.0.1\r\naccept: application/json\r\nX-Injected: aSet-Cookie: b\r\n");
+		assert_eq!(HeaderMap::new().to_string(), "");
+	}
+	
+	#[test]
+	fn keeps_fields_valid_utf8() {
+		let mut headers = HeaderMap::new();
+		headers.append_slices(Bytes::from_static(b"X-Name"), Bytes::from_static(b"caf\xe9"));
+		
+		assert_eq!(headers.get("x-name"), Some("caf\u{fffd}"));
+	}
+}
@@ -0,0 +1,126 @@
+use std::net::IpAddr;
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::{fs, time::interval};
+use crate::utils::cidr_trie::CidrTrie;
+use crate::{ALLOWED_CIDRS, DENIED_CIDRS, IP_LIST_RELOAD_INTERVAL_SECS};
+
+/// Networks of a list, searchable and as they were given.
+struct IpList {
+	trie: CidrTrie,
+	cidrs: Vec<String>,
+}
+
+struct IpLists {
+	allowed: IpList,
+	denied: IpList,
+}
+
+static IP_LISTS: LazyLock<RwLock<IpLists>> = LazyLock::new(|| {
+	RwLock::new(IpLists {
+		allowed: build_list(ALLOWED_CIDRS.iter().copied(), "allowlist"),
+		denied: build_list(DENIED_CIDRS.iter().copied(), "denylist"),
+	})
+});
+
+/// Allow and deny lists of networks, made of `ALLOWED_CIDRS` / `DENIED_CIDRS` plus the
+/// entries of optional list files that are reloaded whenever they change on disk.
+/// Allowed clients bypass rate limiting; denied ones are dropped at accept time, even
+/// when they are allowed as well.
+pub struct IpFilter;
+
+impl IpFilter {
+	pub fn is_allowed(ip: IpAddr) -> bool {
+		IP_LISTS.read().map(|lists| lists.allowed.trie.contains(ip)).unwrap_or(false)
+	}
+	
+	pub fn is_denied(ip: IpAddr) -> bool {
+		IP_LISTS.read().map(|lists| lists.denied.trie.contains(ip)).unwrap_or(false)
+	}
+	
+	/// Networks allowed right now, the list files included.
+	pub fn allowed_cidrs() -> Vec<String> {
+		IP_LISTS.read().map(|lists| lists.allowed.cidrs.clone()).unwrap_or_default()
+	}
+	
+	/// Networks denied right now, the list files included.
+	pub fn denied_cidrs() -> Vec<String> {
+		IP_LISTS.read().map(|lists| lists.denied.cidrs.clone()).unwrap_or_default()
+	}
+	
+	/// Polls the list files and swaps in the new lists whenever one of them changed.
+	pub async fn watch(allowlist_path: Option<String>, denylist_path: Option<String>) {
+		let mut ticker = interval(Duration::from_secs(IP_LIST_RELOAD_INTERVAL_SECS));
+		let mut allowlist_modified: Option<SystemTime> = None;
+		let mut denylist_modified: Option<SystemTime> = None;
+		loop {
+			ticker.tick().await;
+			swap_lists(allowlist_path.as_deref(), denylist_path.as_deref(), &mut allowlist_modified, &mut denylist_modified).await;
+		}
+	}
+	
+	/// Loads the list files once, for commands that do not keep running.
+	pub async fn load(allowlist_path: Option<&str>, denylist_path: Option<&str>) {
+		swap_lists(allowlist_path, denylist_path, &mut None, &mut None).await;
+	}
+}
+
+async fn swap_lists(allowlist_path: Option<&str>, denylist_path: Option<&str>, allowlist_modified: &mut Option<SystemTime>, denylist_modified: &mut Option<SystemTime>) {
+	if let Some(allowed) = reload(allowlist_path, allowlist_modified, ALLOWED_CIDRS, "allowlist").await
+		&& let Ok(mut lists) = IP_LISTS.write() {
+		lists.allowed = allowed;
+	}
+	
+	if let Some(denied) = reload(denylist_path, denylist_modified, DENIED_CIDRS, "denylist").await
+		&& let Ok(mut lists) = IP_LISTS.write() {
+		lists.denied = denied;
+	}
+}
+
+async fn reload(path: Option<&str>, last_modified: &mut Option<SystemTime>, defaults: &[&str], name: &str) -> Option<IpList> {
+	let path = path?;
+	let modified = match fs::metadata(path).await.and_then(|metadata| metadata.modified()) {
+		Ok(modified) => modified,
+		Err(e) => {
+			eprintln!("Failed to read {} {}: {}", name, path, e);
+			return None;
+		}
+	};
+	
+	if *last_modified == Some(modified) {
+		return None;
+	}
+	
+	let content = match fs::read_to_string(path).await {
+		Ok(content) => content,
+		Err(e) => {
+			eprintln!("Failed to read {} {}: {}", name, path, e);
+			return None;
+		}
+	};
+	
+	*last_modified = Some(modified);
+	
+	// One network per line, `#` starts a comment
+	let entries = content
+		.lines()
+		.map(|line| line.split('#').next().unwrap_or("").trim())
+		.filter(|line| !line.is_empty());
+	
+	let list = build_list(defaults.iter().copied().chain(entries), name);
+	println!("Loaded {} from {}", name, path);
+	
+	Some(list)
+}
+
+fn build_list<'a>(cidrs: impl Iterator<Item = &'a str>, name: &str) -> IpList {
+	let mut list = IpList { trie: CidrTrie::new(), cidrs: Vec::new() };
+	for cidr in cidrs {
+		match list.trie.insert(cidr) {
+			Ok(_) => list.cidrs.push(String::from(cidr)),
+			Err(e) => eprintln!("Skipping {} entry: {}", name, e),
+		}
+	}
+	
+	list
+}
@@ -0,0 +1,164 @@
+use std::sync::{LazyLock, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use crate::enums::RoutePriority;
+use crate::{MAX_CONCURRENCY_LIMIT, MIN_CONCURRENCY_LIMIT, INITIAL_CONCURRENCY_LIMIT, TARGET_LATENCY_MS};
+
+const BACKOFF_RATIO: f64 = 0.9;
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static LIMIT: LazyLock<Mutex<AimdLimit>> = LazyLock::new(|| Mutex::new(AimdLimit::new(INITIAL_CONCURRENCY_LIMIT as f64)));
+
+/// Server-wide concurrency limit adapted with AIMD: a request slower than
+/// `TARGET_LATENCY_MS` shrinks the limit by 10%, every fast one completed while the
+/// server was at least half busy grows it by one.
+pub struct LoadShedder;
+
+/// The limit and when it last shrank. Requests started before that ran under the old
+/// limit, so their latency says nothing of the new one: a burst of slow responses backs
+/// off once per round trip instead of once per response.
+struct AimdLimit {
+	limit: f64,
+	last_backoff: Option<Instant>,
+}
+
+impl AimdLimit {
+	fn new(limit: f64) -> Self {
+		Self { limit, last_backoff: None }
+	}
+	
+	fn record(&mut self, started: Instant, finished: Instant, in_flight: usize) {
+		if finished.saturating_duration_since(started) > Duration::from_millis(TARGET_LATENCY_MS) {
+			if self.last_backoff.is_none_or(|last_backoff| started >= last_backoff) {
+				self.limit = (self.limit * BACKOFF_RATIO).max(MIN_CONCURRENCY_LIMIT as f64);
+				self.last_backoff = Some(finished);
+			}
+		} else if in_flight as f64 * 2.0 >= self.limit {
+			self.limit = (self.limit + 1.0).min(MAX_CONCURRENCY_LIMIT as f64);
+		}
+	}
+}
+
+/// Holds a slot of the concurrency limit and reports the handler latency once dropped.
+///
+/// The slot is taken as soon as a request is admitted, but waiting for a bulkhead and
+/// reading the body are up to other services and the client, so the latency is only
+/// timed from `start`. A request that never reached its handler reports nothing.
+pub struct InFlightGuard {
+	started: Option<Instant>,
+	in_flight: usize,
+}
+
+impl LoadShedder {
+	pub fn acquire(priority: RoutePriority) -> Option<InFlightGuard> {
+		let allowed = ((Self::limit() * priority.limit_share()) as usize).max(1);
+		let mut current = IN_FLIGHT.load(Ordering::Acquire);
+		loop {
+			if current >= allowed {
+				return None;
+			}
+			
+			match IN_FLIGHT.compare_exchange_weak(current, current + 1, Ordering::AcqRel, Ordering::Acquire) {
+				Ok(_) => {
+					return Some(InFlightGuard {
+						started: None,
+						in_flight: current + 1,
+					});
+				},
+				Err(actual) => current = actual,
+			}
+		}
+	}
+	
+	pub fn in_flight() -> usize {
+		IN_FLIGHT.load(Ordering::Acquire)
+	}
+	
+	pub fn limit() -> f64 {
+		LIMIT.lock().map(|limit| limit.limit).unwrap_or(INITIAL_CONCURRENCY_LIMIT as f64)
+	}
+	
+	fn record(started: Instant, in_flight: usize) {
+		if let Ok(mut limit) = LIMIT.lock() {
+			limit.record(started, Instant::now(), in_flight);
+		}
+	}
+}
+
+impl InFlightGuard {
+	/// Starts the latency clock, once the request is about to be handled.
+	pub fn start(&mut self) {
+		self.started = Some(Instant::now());
+	}
+}
+
+impl Drop for InFlightGuard {
+	fn drop(&mut self) {
+		IN_FLIGHT.fetch_sub(1, Ordering::AcqRel);
+		if let Some(started) = self.started {
+			LoadShedder::record(started, self.in_flight);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const SLOW: Duration = Duration::from_millis(TARGET_LATENCY_MS + 1);
+	
+	#[test]
+	fn grows_by_one_while_busy() {
+		let mut limit = AimdLimit::new(100.0);
+		let start = Instant::now();
+		
+		limit.record(start, start, 50);
+		assert_eq!(limit.limit, 101.0);
+		
+		// Fast, but with too few requests in flight to tell whether more would be
+		limit.record(start, start, 10);
+		assert_eq!(limit.limit, 101.0);
+		
+		let mut limit = AimdLimit::new(MAX_CONCURRENCY_LIMIT as f64);
+		limit.record(start, start, MAX_CONCURRENCY_LIMIT);
+		assert_eq!(limit.limit, MAX_CONCURRENCY_LIMIT as f64);
+	}
+	
+	#[test]
+	fn backs_off_once_per_round_trip() {
+		let mut limit = AimdLimit::new(100.0);
+		let start = Instant::now();
+		
+		// A burst of slow responses to requests that were all in flight together
+		for _ in 0..10 {
+			limit.record(start, start + SLOW, 90);
+		}
+		assert_eq!(limit.limit, 90.0);
+		
+		// A request started after the backoff is slow under the new limit too
+		limit.record(start + SLOW, start + SLOW * 2, 80);
+		assert_eq!(limit.limit, 81.0);
+	}
+	
+	#[test]
+	fn times_only_the_handler() {
+		let mut guard = LoadShedder::acquire(RoutePriority::Critical).unwrap();
+		assert!(guard.started.is_none(), "admitted, not handled yet");
+		
+		let before = Instant::now();
+		guard.start();
+		assert!(guard.started.is_some_and(|started| started >= before));
+	}
+	
+	#[test]
+	fn never_backs_off_below_the_floor() {
+		let mut limit = AimdLimit::new(MIN_CONCURRENCY_LIMIT as f64 + 1.0);
+		let mut start = Instant::now();
+		for _ in 0..5 {
+			limit.record(start, start + SLOW, 1);
+			start += SLOW;
+		}
+		
+		assert_eq!(limit.limit, MIN_CONCURRENCY_LIMIT as f64);
+	}
+}
@@ -0,0 +1,52 @@
+use std::fmt::Write;
+use crate::core::{Bulkhead, ConnectionLimiter, LoadShedder, Tarpit};
+use crate::MAX_CONNECTIONS;
+
+pub struct Metrics;
+
+impl Metrics {
+	/// Renders the server gauges in the Prometheus text exposition format.
+	pub fn render() -> String {
+		let mut out = String::new();
+		
+		let _ = writeln!(out, "# TYPE rustrate_in_flight_requests gauge");
+		let _ = writeln!(out, "rustrate_in_flight_requests {}", LoadShedder::in_flight());
+		let _ = writeln!(out, "# TYPE rustrate_concurrency_limit gauge");
+		let _ = writeln!(out, "rustrate_concurrency_limit {:.2}", LoadShedder::limit());
+		
+		let _ = writeln!(out, "# TYPE rustrate_open_connections gauge");
+		let _ = writeln!(out, "rustrate_open_connections {}", ConnectionLimiter::open());
+		let _ = writeln!(out, "# TYPE rustrate_max_connections gauge");
+		let _ = writeln!(out, "rustrate_max_connections {}", MAX_CONNECTIONS);
+		let _ = writeln!(out, "# TYPE rustrate_connected_clients gauge");
+		let _ = writeln!(out, "rustrate_connected_clients {}", ConnectionLimiter::clients());
+		let _ = writeln!(out, "# TYPE rustrate_rejected_connections_total counter");
+		let _ = writeln!(out, "rustrate_rejected_connections_total {}", ConnectionLimiter::rejected());
+		
+		let _ = writeln!(out, "# TYPE rustrate_tarpitted_connections gauge");
+		let _ = writeln!(out, "rustrate_tarpitted_connections {}", Tarpit::active());
+		
+		let occupancy = Bulkhead::occupancy();
+		let _ = writeln!(out, "# TYPE rustrate_bulkhead_in_flight gauge");
+		for route in &occupancy {
+			let _ = writeln!(out, "rustrate_bulkhead_in_flight{{route=\"{}\"}} {}", route.prefix, route.in_flight);
+		}
+		
+		let _ = writeln!(out, "# TYPE rustrate_bulkhead_max_in_flight gauge");
+		for route in &occupancy {
+			let _ = writeln!(out, "rustrate_bulkhead_max_in_flight{{route=\"{}\"}} {}", route.prefix, route.max_in_flight);
+		}
+		
+		let _ = writeln!(out, "# TYPE rustrate_bulkhead_queued gauge");
+		for route in &occupancy {
+			let _ = writeln!(out, "rustrate_bulkhead_queued{{route=\"{}\"}} {}", route.prefix, route.queued);
+		}
+		
+		let _ = writeln!(out, "# TYPE rustrate_bulkhead_max_queued gauge");
+		for route in &occupancy {
+			let _ = writeln!(out, "rustrate_bulkhead_max_queued{{route=\"{}\"}} {}", route.prefix, route.max_queued);
+		}
+		
+		out
+	}
+}
@@ -1,7 +1,41 @@
 mod request;
+mod header_map;
+mod query;
+mod path_normalizer;
+mod uri;
+mod chunked_decoder;
 mod response;
 mod rate_limiter;
+mod load_shedder;
+mod bulkhead;
+mod metrics;
+mod connection_limiter;
+mod handshake_limiter;
+mod tarpit;
+mod ip_filter;
+mod geoip;
+mod connection_info;
+mod challenge;
+mod tls_fingerprint;
+mod simulator;
 
 pub use request::HttpRequest;
+pub use header_map::HeaderMap;
+pub use query::Query;
+pub use path_normalizer::PathNormalizer;
+pub use uri::Uri;
+pub use chunked_decoder::ChunkedDecoder;
 pub use response::HttpResponse;
-pub use rate_limiter::RateLimiter;
\ No newline at end of file
+pub use rate_limiter::{RateLimiter, SharedLimit};
+pub use load_shedder::LoadShedder;
+pub use bulkhead::Bulkhead;
+pub use metrics::Metrics;
+pub use connection_limiter::ConnectionLimiter;
+pub use handshake_limiter::HandshakeLimiter;
+pub use tarpit::Tarpit;
+pub use ip_filter::IpFilter;
+pub use geoip::{GeoInfo, GeoIp};
+pub use connection_info::ConnectionInfo;
+pub use challenge::Challenge;
+pub use tls_fingerprint::TlsFingerprint;
+pub use simulator::Simulator;
\ No newline at end of file
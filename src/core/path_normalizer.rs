@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+use crate::enums::{EncodedSlash, HttpError, InvalidUtf8};
+use crate::{ENCODED_SLASH, INVALID_UTF8_PATH, MERGE_SLASHES};
+
+/// Normalizes request paths before anything routes on them.
+///
+/// Segments are split on the raw path and percent-decoded one by one, so an escaped
+/// `/` is handled as `ENCODED_SLASH` says instead of silently becoming a separator.
+/// Dot-segments are then removed as of RFC 3986 section 5.2.4, decoded ones included,
+/// and `..` at the root is dropped: the result always starts with `/` and never holds a
+/// `.` or `..` segment, so it cannot step out of whatever root it is resolved against.
+/// Control characters and backslashes, raw or escaped, are rejected for the same reason.
+/// Decoded `%`, `?` and `#` are escaped again, so normalizing the result changes nothing
+/// and it never reads as a query or a fragment.
+pub struct PathNormalizer;
+
+impl PathNormalizer {
+	/// Normalized form of an origin-form `path`, borrowed when it already is.
+	pub fn normalize(path: &str) -> Result<Cow<'_, str>, HttpError> {
+		let Some(relative) = path.strip_prefix('/') else {
+			return Err(HttpError::InvalidRequestTarget);
+		};
+		
+		if Self::is_normalized(relative) {
+			return Ok(Cow::Borrowed(path));
+		}
+		
+		let raw_segments: Vec<&str> = relative.split('/').collect();
+		let mut segments: Vec<String> = Vec::with_capacity(raw_segments.len());
+		for (i, raw_segment) in raw_segments.iter().enumerate() {
+			let segment = Self::decode_segment(raw_segment)?;
+			let is_last = i + 1 == raw_segments.len();
+			
+			if ENCODED_SLASH == EncodedSlash::Decode {
+				let parts: Vec<&str> = segment.split('/').collect();
+				for (j, part) in parts.iter().enumerate() {
+					push_segment(&mut segments, part, is_last && j + 1 == parts.len());
+				}
+			} else {
+				push_segment(&mut segments, &segment, is_last);
+			}
+		}
+		
+		Ok(Cow::Owned(format!("/{}", segments.join("/"))))
+	}
+	
+	fn is_normalized(relative: &str) -> bool {
+		if relative.contains(['%', '\\']) {
+			return false;
+		}
+		
+		let mut segments = relative.split('/').peekable();
+		while let Some(segment) = segments.next() {
+			let is_last = segments.peek().is_none();
+			if matches!(segment, "." | "..") || (segment.is_empty() && MERGE_SLASHES && !is_last) {
+				return false;
+			}
+		}
+		
+		true
+	}
+	
+	fn decode_segment(segment: &str) -> Result<String, HttpError> {
+		let bytes = segment.as_bytes();
+		let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+		let mut i: usize = 0;
+		while i < bytes.len() {
+			if bytes[i] != b'%' {
+				decoded.push(bytes[i]);
+				i += 1;
+				continue;
+			}
+			
+			let escape = bytes.get(i + 1..i + 3).ok_or(HttpError::InvalidPath)?;
+			let byte = std::str::from_utf8(escape)
+				.ok()
+				.and_then(|hex| u8::from_str_radix(hex, 16).ok())
+				.ok_or(HttpError::InvalidPath)?;
+			
+			match (byte, ENCODED_SLASH) {
+				(b'/', EncodedSlash::Reject) => return Err(HttpError::EncodedSlash),
+				(b'/', EncodedSlash::Keep) => decoded.extend_from_slice(&bytes[i..i + 3]),
+				(b'%' | b'?' | b'#', _) => decoded.extend_from_slice(format!("%{:02X}", byte).as_bytes()),
+				_ => decoded.push(byte),
+			}
+			
+			i += 3;
+		}
+		
+		if decoded.iter().any(|byte| byte.is_ascii_control() || *byte == b'\\') {
+			return Err(HttpError::InvalidPath);
+		}
+		
+		match INVALID_UTF8_PATH {
+			InvalidUtf8::Reject => String::from_utf8(decoded).map_err(|_| HttpError::DecodeUrlFailed),
+			InvalidUtf8::Replace => Ok(String::from_utf8_lossy(&decoded).into_owned()),
+		}
+	}
+}
+
+/*
+ * `.` is dropped and `..` drops the segment before it, if any. Either keeps a trailing
+ * slash when it ends the path, as `/a/.` means `/a/`. Empty segments only survive at the
+ * end or when MERGE_SLASHES is off.
+ */
+fn push_segment(segments: &mut Vec<String>, segment: &str, is_last: bool) {
+	match segment {
+		"." => (),
+		".." => {
+			segments.pop();
+		},
+		"" if MERGE_SLASHES && !is_last => return,
+		_ => {
+			segments.push(String::from(segment));
+			return;
+		},
+	}
+	
+	if is_last {
+		segments.push(String::new());
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn normalizes_paths() {
+		let normalized = [
+			("/", "/"),
+			("/static/app.js", "/static/app.js"),
+			("/a/", "/a/"),
+			("/a/./b/../c", "/a/c"),
+			("/a/b/..", "/a/"),
+			("/a/.", "/a/"),
+			("/..", "/"),
+			("/../../etc/passwd", "/etc/passwd"),
+			("/static/%2E%2E/%2e%2e/etc/passwd", "/etc/passwd"),
+			("/static/.%2E/secret", "/secret"),
+			("//a///b//", "/a/b/"),
+			("/caf%C3%A9", "/café"),
+			("/a%20b", "/a b"),
+			("/%252e%252e/etc", "/%252e%252e/etc"),
+			("/100%25", "/100%25"),
+			("/a%3fb%23c", "/a%3Fb%23c"),
+		];
+		for (raw, path) in normalized {
+			assert_eq!(PathNormalizer::normalize(raw), Ok(Cow::Borrowed(path)), "{}", raw);
+		}
+		
+		let rejected = [
+			("/static/..%2F..%2Fetc/passwd", HttpError::EncodedSlash),
+			("/a%2fb", HttpError::EncodedSlash),
+			("/a%00b", HttpError::InvalidPath),
+			("/a%0Db", HttpError::InvalidPath),
+			("/..%5C..%5Cwindows", HttpError::InvalidPath),
+			("/a\\b", HttpError::InvalidPath),
+			("/a%zzb", HttpError::InvalidPath),
+			("/a%2", HttpError::InvalidPath),
+			("/caf%E9", HttpError::DecodeUrlFailed),
+			("a/b", HttpError::InvalidRequestTarget),
+		];
+		for (raw, error) in rejected {
+			assert_eq!(PathNormalizer::normalize(raw), Err(error), "{}", raw);
+		}
+	}
+	
+	#[test]
+	fn borrows_normalized_paths() {
+		assert!(matches!(PathNormalizer::normalize("/static/app.js"), Ok(Cow::Borrowed(_))));
+		assert!(matches!(PathNormalizer::normalize("/static/./app.js"), Ok(Cow::Owned(_))));
+	}
+	
+	#[test]
+	fn normalizes_idempotently() {
+		let paths = [
+			"/a/./b/../c",
+			"/static/%2E%2E/%2e%2e/etc/passwd",
+			"/%252e%252e/etc",
+			"/%25252e%25252e/etc",
+			"/100%25/%2525",
+			"/a%3Fb%23c",
+			"/caf%C3%A9/%20/",
+			"//a///b//.",
+		];
+		for path in paths {
+			let normalized = PathNormalizer::normalize(path).unwrap();
+			assert_eq!(PathNormalizer::normalize(&normalized).unwrap(), normalized, "{}", path);
+		}
+	}
+}
@@ -0,0 +1,140 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use crate::enums::QueryError;
+use crate::traits::FromQuery;
+
+/// Parameters of a query string, decoded as form data: `+` stands for a space and
+/// escapes are decoded after splitting, so `%26` and `%3D` never act as separators.
+///
+/// A name may carry several values, kept in the order they were given.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+	pairs: Vec<(String, String)>,
+}
+
+impl Query {
+	/// Parses the part of a request target after `?`.
+	pub fn parse(raw: &str) -> Self {
+		let pairs = raw
+			.split('&')
+			.filter(|pair| !pair.is_empty())
+			.map(|pair| {
+				let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+				(decode_component(name), decode_component(value))
+			})
+			.collect();
+		
+		Self { pairs }
+	}
+	
+	/// First value of `name`.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.pairs.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+	}
+	
+	/// Every value of `name`, in order.
+	pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.pairs.iter().filter(move |(key, _)| key == name).map(|(_, value)| value.as_str())
+	}
+	
+	/// The single value of `name` parsed as `T`.
+	pub fn required<T>(&self, name: &str) -> Result<T, QueryError>
+	where
+		T: FromStr,
+		T::Err: Display,
+	{
+		self.optional(name)?.ok_or_else(|| QueryError::Missing(String::from(name)))
+	}
+	
+	/// The value of `name` parsed as `T`, `None` when it is absent.
+	pub fn optional<T>(&self, name: &str) -> Result<Option<T>, QueryError>
+	where
+		T: FromStr,
+		T::Err: Display,
+	{
+		let mut values = self.get_all(name);
+		let Some(value) = values.next() else {
+			return Ok(None);
+		};
+		
+		if values.next().is_some() {
+			return Err(QueryError::Duplicate(String::from(name)));
+		}
+		
+		parse_value(name, value).map(Some)
+	}
+	
+	/// Every value of `name` parsed as `T`, empty when it is absent.
+	pub fn all<T>(&self, name: &str) -> Result<Vec<T>, QueryError>
+	where
+		T: FromStr,
+		T::Err: Display,
+	{
+		self.get_all(name).map(|value| parse_value(name, value)).collect()
+	}
+	
+	pub fn deserialize<T: FromQuery>(&self) -> Result<T, QueryError> {
+		T::from_query(self)
+	}
+}
+
+fn parse_value<T>(name: &str, value: &str) -> Result<T, QueryError>
+where
+	T: FromStr,
+	T::Err: Display,
+{
+	value.parse().map_err(|e: T::Err| QueryError::Invalid {
+		name: String::from(name),
+		value: String::from(value),
+		reason: e.to_string(),
+	})
+}
+
+fn decode_component(component: &str) -> String {
+	let component = component.replace('+', " ");
+	String::from_utf8_lossy(&urlencoding::decode_binary(component.as_bytes())).into_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn decodes_and_parses_values() {
+		let query = Query::parse("x=1+2&x=%26&flag&n=5&name=caf%C3%A9&&eq=a%3Db");
+		assert_eq!(query.get_all("x").collect::<Vec<&str>>(), ["1 2", "&"]);
+		assert_eq!(query.get("x"), Some("1 2"));
+		assert_eq!(query.get("flag"), Some(""));
+		assert_eq!(query.get("eq"), Some("a=b"));
+		assert_eq!(query.get("missing"), None);
+		assert_eq!(query.required::<u8>("n"), Ok(5));
+		assert_eq!(query.required::<String>("name"), Ok(String::from("café")));
+		assert_eq!(query.optional::<u8>("limit"), Ok(None));
+		assert_eq!(query.all::<String>("x"), Ok(vec![String::from("1 2"), String::from("&")]));
+		assert_eq!(query.all::<u8>("limit"), Ok(Vec::new()));
+		assert_eq!(query.required::<u8>("limit"), Err(QueryError::Missing(String::from("limit"))));
+		assert_eq!(query.required::<String>("x"), Err(QueryError::Duplicate(String::from("x"))));
+		assert!(matches!(query.required::<u8>("flag"), Err(QueryError::Invalid { name, .. }) if name == "flag"));
+	}
+	
+	#[test]
+	fn deserializes_into_types() {
+		#[derive(Debug, PartialEq)]
+		struct Page {
+			page: u32,
+			tags: Vec<String>,
+		}
+		
+		impl FromQuery for Page {
+			fn from_query(query: &Query) -> Result<Self, QueryError> {
+				Ok(Self { page: query.optional("page")?.unwrap_or(1), tags: query.all("tag")? })
+			}
+		}
+		
+		assert_eq!(Query::parse("tag=a&tag=b+c").deserialize::<Page>(), Ok(Page { page: 1, tags: vec![String::from("a"), String::from("b c")] }));
+		
+		let error = Query::parse("page=two").deserialize::<Page>().unwrap_err();
+		assert_eq!(error.to_string(), "invalid value \"two\" for query parameter `page`: invalid digit found in string");
+	}
+}
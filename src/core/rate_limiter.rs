@@ -1,69 +1,383 @@
-use std::collections::HashMap;
-use std::sync::{LazyLock, RwLock};
-use std::time::{Duration, Instant};
-use tokio::{time::interval};
-use crate::MAX_REQUEST_PER_MINUTE;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{fs, time::interval};
+use crate::core::{ConnectionInfo, GeoIp, IpFilter, TlsFingerprint};
+use crate::enums::RateLimitDecision;
+use crate::stores::MemoryStore;
+use crate::traits::{RateLimitStore, StoreBan, StoreEntry, StoreError};
+use crate::{MAX_REQUEST_PER_MINUTE, POW_CHALLENGE_THRESHOLD, RATE_LIMIT_SNAPSHOT_INTERVAL_SECS, TARPIT_THRESHOLD, VERIFIED_MAX_REQUEST_PER_MINUTE};
 
-static RATE_LIMITER: LazyLock<RwLock<HashMap<String, (u8, Instant)>>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+static RATE_LIMITER: OnceLock<Box<dyn RateLimitStore>> = OnceLock::new();
+
+const SNAPSHOT_HEADER: &str = "rustrate-snapshot/1";
 
 pub struct RateLimiter;
 
-impl RateLimiter {
-	pub fn add(ip: String) {
-		if let Ok(mut map) = RATE_LIMITER.write() {
-			let (counter, time): (u8, Instant) = {
-				if let Some((counter, last_time)) = map.get(&ip) {
-					let new_counter = if RateLimiter::is_alive(*last_time) {
-						counter.saturating_add(1)
-					} else {
-						1
-					};
-					
-					(new_counter, Instant::now())
-				}
-				else {
-					(1, Instant::now())
-				}
-			};
+/// Budget shared by several clients, of a location or a TLS fingerprint, that rejected a request.
+pub struct SharedLimit {
+	pub key: String,
+	pub count: u8,
+	pub limit: u8,
+}
 
-			map.insert(ip, (counter, time));
+impl RateLimiter {
+	/// Installs the store backing every rate limit decision. Must be called before the
+	/// first request; without it the limiter falls back to an in-process `MemoryStore`.
+	pub fn init(store: Box<dyn RateLimitStore>) {
+		if RATE_LIMITER.set(store).is_err() {
+			eprintln!("Rate limiter store is already initialized");
 		}
 	}
 	
-	pub fn is_blocked(ip: &String) -> bool {
-		if let Some((count, last_time)) = RateLimiter::get(ip) {
-			if RateLimiter::is_alive(last_time) && count >= MAX_REQUEST_PER_MINUTE {
-				return true;
-			}
+	pub async fn add(ip: &str) -> u8 {
+		Self::penalize(ip, 1).await
+	}
+	
+	/// Counts `hits` requests at once against `ip`, e.g. for clients caught misbehaving.
+	pub async fn penalize(ip: &str, hits: u8) -> u8 {
+		Self::count(Self::store(), ip, hits).await
+	}
+	
+	/// Counts a request from the client of `info` and decides how to answer it.
+	/// Allowlisted clients are never limited, `verified` ones solved a proof-of-work challenge.
+	pub async fn check(info: &ConnectionInfo, verified: bool) -> RateLimitDecision {
+		let limit = if verified { VERIFIED_MAX_REQUEST_PER_MINUTE } else { MAX_REQUEST_PER_MINUTE };
+		let (decision, shared_limit) = Self::check_at(Self::store(), info, limit, verified).await;
+		
+		// Logged once per window, when the budget runs out, not for every request refused after
+		if let Some(shared_limit) = shared_limit && shared_limit.count == shared_limit.limit {
+			eprintln!("{} used up its budget of {}/min with {} from {}", shared_limit.key, shared_limit.limit, info.addr.ip(), info.geo);
 		}
 		
-		false
+		decision
 	}
 	
-	pub fn get(ip: &String) -> Option<(u8, Instant)> {
-		if let Ok(map) = RATE_LIMITER.read() {
-			if let Some(val) = map.get(ip) {
-				return Some(*val);
+	/// `check` against `store` with a per-client `limit`, for callers keeping their own
+	/// counters such as the simulator. Also returns the shared budget that rejected the
+	/// request, if one did.
+	pub async fn check_at(store: &dyn RateLimitStore, info: &ConnectionInfo, limit: u8, verified: bool) -> (RateLimitDecision, Option<SharedLimit>) {
+		if IpFilter::is_allowed(info.addr.ip()) {
+			return (RateLimitDecision::Allow, None);
+		}
+		
+		match store.ban_ends(&Self::keys_of(info)).await {
+			Ok(ends) if ends.iter().any(Option::is_some) => return (RateLimitDecision::Reject, None),
+			Ok(_) => (),
+			Err(e) => eprintln!("Failed to look up bans: {}", e),
+		}
+		
+		// The counters returned by the store are the ones decided on, a second read would cost another round trip
+		let count = Self::count(store, &info.addr.ip().to_string(), 1).await;
+		
+		// Budgets shared by several clients, each against its own limit
+		for (key, key_limit) in GeoIp::rate_limits(&info.geo).into_iter().chain(TlsFingerprint::rate_limits(info)) {
+			let key_count = Self::count(store, &key, 1).await;
+			if Self::is_limited(key_count, key_limit) {
+				return (RateLimitDecision::Reject, Some(SharedLimit { key, count: key_count, limit: key_limit }));
 			}
 		}
 		
-		None
+		(Self::decision_for(Some(count), limit, verified), None)
+	}
+	
+	/// Whether a key that made `count` requests in the current window, this one included,
+	/// has used up `limit`. Every limit, per client or shared, is held to this comparison.
+	pub fn is_limited(count: u8, limit: u8) -> bool {
+		count >= limit
+	}
+	
+	/// Decision for a client that made `count` requests in the current window against `limit`.
+	/// Clients at the limit are rejected; those at or past `TARPIT_THRESHOLD`, if set,
+	/// are considered abusive and tarpitted instead. Unverified clients past
+	/// `POW_CHALLENGE_THRESHOLD` are challenged before they reach the limit.
+	pub fn decision_for(count: Option<u8>, limit: u8, verified: bool) -> RateLimitDecision {
+		match count {
+			Some(count) if Self::is_limited(count, limit) && TARPIT_THRESHOLD.is_some_and(|threshold| count >= threshold) => RateLimitDecision::Tarpit,
+			Some(count) if Self::is_limited(count, limit) => RateLimitDecision::Reject,
+			Some(count) if !verified && POW_CHALLENGE_THRESHOLD.is_some_and(|threshold| count >= threshold) => RateLimitDecision::Challenge,
+			_ => RateLimitDecision::Allow,
+		}
+	}
+	
+	pub async fn get(ip: &str) -> Result<Option<u8>, StoreError> {
+		Self::store().get(ip).await
+	}
+	
+	/// Live counters sorted by usage, busiest first.
+	pub async fn top(limit: usize) -> Result<Vec<(String, u8)>, StoreError> {
+		let mut entries = Self::store().entries().await?;
+		entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+		entries.truncate(limit);
+		
+		Ok(entries)
+	}
+	
+	/// Clears the counter and the ban of `key`.
+	pub async fn reset(key: &str) -> Result<(), StoreError> {
+		Self::unban(key).await?;
+		Self::store().remove(key).await
+	}
+	
+	/// Rejects every request matching `key` (a client IP, `country:XX`, `asn:N` or
+	/// `ja4:<fingerprint>`) for `duration`, whatever its counters say. Fails with
+	/// `ErrorKind::InvalidInput` when the end of the ban is past what the clock can hold.
+	pub async fn ban(key: &str, duration: Duration) -> Result<(), StoreError> {
+		let Some(until) = SystemTime::now().checked_add(duration) else {
+			return Err(Box::new(IoError::new(ErrorKind::InvalidInput, "ban duration is out of range")));
+		};
+		
+		Self::store().ban(key, until).await
+	}
+	
+	pub async fn unban(key: &str) -> Result<bool, StoreError> {
+		Self::store().unban(key).await
+	}
+	
+	/// Time left on the ban of `key`, `None` when it is not banned.
+	pub async fn ban_remaining(key: &str) -> Result<Option<Duration>, StoreError> {
+		let ends = Self::store().ban_ends(&[String::from(key)]).await?;
+		let end = ends.into_iter().flatten().next();
+		
+		Ok(end.and_then(|end| end.duration_since(SystemTime::now()).ok()).filter(|remaining| !remaining.is_zero()))
+	}
+	
+	pub async fn bans() -> Result<Vec<(String, Duration)>, StoreError> {
+		let now = SystemTime::now();
+		let bans = Self::store().bans().await?;
+		
+		Ok(bans.into_iter().filter_map(|ban| Some((ban.key, ban.until.duration_since(now).ok()?))).collect())
+	}
+	
+	/// Every key a ban on the client of `info` can be keyed on.
+	fn keys_of(info: &ConnectionInfo) -> Vec<String> {
+		let mut keys: Vec<String> = vec![info.addr.ip().to_string()];
+		if let Some(country) = &info.geo.country {
+			keys.push(format!("country:{}", country));
+		}
+		
+		if let Some(asn) = info.geo.asn {
+			keys.push(format!("asn:{}", asn));
+		}
+		
+		if let Some(fingerprint) = &info.tls_fingerprint {
+			keys.push(format!("ja4:{}", fingerprint));
+		}
+		
+		keys
 	}
 	
 	pub async fn cleanup() {
 		let mut ticker = interval(Duration::from_secs(5));
 		loop {
 			ticker.tick().await;
-			let now = Instant::now();
-			if let Ok(mut map) = RATE_LIMITER.write() {
-				map.retain(|_, &mut (_, last_time)| {
-					now.duration_since(last_time) < Duration::from_secs(60)
-				});
+			if let Err(e) = Self::store().cleanup().await {
+				eprintln!("Failed to clean up rate limiter: {}", e);
 			}
 		}
 	}
 	
-	fn is_alive(time: Instant) -> bool {
-		time.elapsed() < Duration::from_secs(60)
+	pub async fn snapshot(path: &str) -> Result<usize, IoError> {
+		let entries: Vec<StoreEntry> = Self::store().snapshot();
+		let bans: Vec<StoreBan> = Self::store().snapshot_bans();
+		
+		// Written next to the target first, so a crash mid-write never leaves a truncated snapshot
+		let tmp_path = format!("{}.tmp", path);
+		fs::write(&tmp_path, Self::encode_snapshot(&entries, &bans)).await?;
+		fs::rename(&tmp_path, path).await?;
+		
+		Ok(entries.len() + bans.len())
+	}
+	
+	/// Loads a snapshot into the store. A snapshot that does not read back whole is not
+	/// restored at all, expired counters and bans are left to the store to drop.
+	pub async fn restore(path: &str) -> Result<usize, IoError> {
+		let content = match fs::read_to_string(path).await {
+			Ok(content) => content,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+			Err(e) => return Err(e),
+		};
+		
+		let (entries, bans) = Self::decode_snapshot(&content)?;
+		let count = entries.len() + bans.len();
+		Self::store().restore(entries);
+		Self::store().restore_bans(bans);
+		
+		Ok(count)
+	}
+	
+	/*
+	 * Snapshot layout, one record per line:
+	 * rustrate-snapshot/1
+	 * count <key> <count> <last hit as unix milliseconds>
+	 * ban <key> <end of the ban as unix milliseconds>
+	 * end <number of records>
+	 */
+	fn encode_snapshot(entries: &[StoreEntry], bans: &[StoreBan]) -> String {
+		let mut content = format!("{}\n", SNAPSHOT_HEADER);
+		for entry in entries {
+			content.push_str(&format!("count {} {} {}\n", entry.key, entry.count, millis(entry.last_seen)));
+		}
+		
+		for ban in bans {
+			content.push_str(&format!("ban {} {}\n", ban.key, millis(ban.until)));
+		}
+		
+		content.push_str(&format!("end {}\n", entries.len() + bans.len()));
+		content
+	}
+	
+	fn decode_snapshot(content: &str) -> Result<(Vec<StoreEntry>, Vec<StoreBan>), IoError> {
+		let invalid = |reason: &str| IoError::new(ErrorKind::InvalidData, format!("Invalid snapshot: {}", reason));
+		
+		let mut lines = content.lines();
+		if lines.next() != Some(SNAPSHOT_HEADER) {
+			return Err(invalid("unknown format or version"));
+		}
+		
+		let mut entries: Vec<StoreEntry> = Vec::new();
+		let mut bans: Vec<StoreBan> = Vec::new();
+		for line in lines.by_ref() {
+			// Keys go first so they may hold spaces, the numbers are split off the end
+			if let Some(record) = line.strip_prefix("count ") {
+				let parts: Vec<&str> = record.rsplitn(3, ' ').collect();
+				let [last_seen, count, key] = parts[..] else {
+					return Err(invalid(line));
+				};
+				
+				let (Ok(count), Ok(last_seen)) = (count.parse::<u8>(), last_seen.parse::<u64>()) else {
+					return Err(invalid(line));
+				};
+				
+				entries.push(StoreEntry { key: String::from(key), count, last_seen: UNIX_EPOCH + Duration::from_millis(last_seen) });
+			} else if let Some(record) = line.strip_prefix("ban ") {
+				let Some((key, Ok(until))) = record.rsplit_once(' ').map(|(key, until)| (key, until.parse::<u64>())) else {
+					return Err(invalid(line));
+				};
+				
+				bans.push(StoreBan { key: String::from(key), until: UNIX_EPOCH + Duration::from_millis(until) });
+			} else if let Some(count) = line.strip_prefix("end ") {
+				if count.parse() != Ok(entries.len() + bans.len()) || lines.next().is_some() {
+					return Err(invalid("record count does not match"));
+				}
+				
+				return Ok((entries, bans));
+			} else {
+				return Err(invalid(line));
+			}
+		}
+		
+		Err(invalid("truncated"))
+	}
+	
+	pub async fn persist(path: String) {
+		let mut ticker = interval(Duration::from_secs(RATE_LIMIT_SNAPSHOT_INTERVAL_SECS));
+		ticker.tick().await;
+		loop {
+			ticker.tick().await;
+			if let Err(e) = Self::snapshot(&path).await {
+				eprintln!("Failed to snapshot rate limiter: {}", e);
+			}
+		}
+	}
+	
+	async fn count(store: &dyn RateLimitStore, key: &str, hits: u8) -> u8 {
+		match store.add(key, hits).await {
+			Ok(counter) => counter,
+			Err(e) => {
+				eprintln!("Failed to update rate limiter: {}", e);
+				0
+			}
+		}
+	}
+	
+	fn store() -> &'static dyn RateLimitStore {
+		RATE_LIMITER.get_or_init(|| Box::new(MemoryStore::new())).as_ref()
+	}
+}
+
+fn millis(time: SystemTime) -> u128 {
+	time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::RATE_LIMIT_WINDOW_SECS;
+	
+	fn at(millis: u64) -> SystemTime {
+		UNIX_EPOCH + Duration::from_millis(millis)
+	}
+	
+	fn entry(key: &str, count: u8, last_seen: SystemTime) -> StoreEntry {
+		StoreEntry { key: String::from(key), count, last_seen }
+	}
+	
+	fn ban(key: &str, until: SystemTime) -> StoreBan {
+		StoreBan { key: String::from(key), until }
+	}
+	
+	#[test]
+	fn round_trips_snapshots() {
+		let entries = [entry("10.0.0.1", 5, at(1_700_000_000_123)), entry("country:FR", 200, at(1_700_000_001_000))];
+		let bans = [ban("ja4:t13d1516h2_8daaf6152771_b0da82dd1658", at(1_800_000_000_000)), ban("key with spaces", at(1_800_000_000_001))];
+		
+		let content = RateLimiter::encode_snapshot(&entries, &bans);
+		assert!(content.starts_with("rustrate-snapshot/1\n"));
+		
+		let (restored_entries, restored_bans) = RateLimiter::decode_snapshot(&content).unwrap();
+		assert_eq!(
+			restored_entries.iter().map(|entry| (entry.key.as_str(), entry.count, entry.last_seen)).collect::<Vec<_>>(),
+			entries.iter().map(|entry| (entry.key.as_str(), entry.count, entry.last_seen)).collect::<Vec<_>>()
+		);
+		assert_eq!(
+			restored_bans.iter().map(|ban| (ban.key.as_str(), ban.until)).collect::<Vec<_>>(),
+			bans.iter().map(|ban| (ban.key.as_str(), ban.until)).collect::<Vec<_>>()
+		);
+		
+		assert_eq!(RateLimiter::decode_snapshot(&RateLimiter::encode_snapshot(&[], &[])).unwrap().0.len(), 0);
+	}
+	
+	#[test]
+	fn drops_expired_entries_and_bans_on_restore() {
+		let now = SystemTime::now();
+		let entries = [entry("fresh", 3, now - Duration::from_secs(1)), entry("expired", 9, now - Duration::from_secs(RATE_LIMIT_WINDOW_SECS + 1))];
+		let bans = [ban("banned", now + Duration::from_secs(60)), ban("served", now - Duration::from_secs(1))];
+		let (entries, bans) = RateLimiter::decode_snapshot(&RateLimiter::encode_snapshot(&entries, &bans)).unwrap();
+		
+		let store = MemoryStore::new();
+		store.restore(entries);
+		store.restore_bans(bans);
+		
+		assert_eq!(store.snapshot().iter().map(|entry| (entry.key.as_str(), entry.count)).collect::<Vec<_>>(), [("fresh", 3)]);
+		assert_eq!(store.snapshot_bans().iter().map(|ban| ban.key.as_str()).collect::<Vec<_>>(), ["banned"]);
+	}
+	
+	#[test]
+	fn rejects_corrupt_and_truncated_snapshots() {
+		let content = RateLimiter::encode_snapshot(&[entry("10.0.0.1", 5, at(1_700_000_000_123))], &[ban("10.0.0.2", at(1_800_000_000_000))]);
+		let is_invalid = |content: &str| RateLimiter::decode_snapshot(content).is_err_and(|err| err.kind() == ErrorKind::InvalidData);
+		
+		// Cut anywhere but the last line break
+		for end in 0..content.len() - 1 {
+			assert!(is_invalid(&content[..end]), "{:?}", &content[..end]);
+		}
+		
+		assert!(is_invalid("10.0.0.1 5 1700000000123\nban 10.0.0.2 1800000000000\n"));
+		assert!(is_invalid(&content.replace("rustrate-snapshot/1", "rustrate-snapshot/2")));
+		assert!(is_invalid(&content.replace(" 5 ", " 500 ")));
+		assert!(is_invalid(&content.replace("ban 10.0.0.2 ", "ban 10.0.0.2 x")));
+		assert!(is_invalid(&content.replace("end 2", "end 3")));
+		assert!(is_invalid(&format!("{}count 10.0.0.3 1 1700000000123\n", content)));
+		assert!(is_invalid(&content.replace("count ", "counter ")));
+	}
+	
+	#[test]
+	fn rejects_the_request_reaching_the_limit() {
+		assert!(!RateLimiter::is_limited(4, 5));
+		assert!(RateLimiter::is_limited(5, 5));
+		
+		assert_eq!(RateLimiter::decision_for(Some(4), 5, true), RateLimitDecision::Allow);
+		assert_eq!(RateLimiter::decision_for(Some(5), 5, true), RateLimitDecision::Reject);
 	}
-}
\ No newline at end of file
+}
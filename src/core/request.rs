@@ -1,20 +1,38 @@
-use std::{ collections::HashMap, error::Error };
+use bytes::Bytes;
 use crate::{
-	enums::{HttpError, HttpMethod, HttpVersion},
-	utils::helper::sanitize_header_value,
-	MAX_HEADERS_LENGTH
+	core::{GeoInfo, HeaderMap, Query, Uri},
+	enums::{BodyFraming, HttpError, HttpMethod, HttpVersion, QueryError, TargetForm},
+	traits::FromQuery,
+	MAX_HEADERS_LENGTH,
+	MAX_HEADERS_SIZE,
+	MAX_REQUEST_TARGET_LENGTH
 };
 
+type RequestLine<'a> = (HttpMethod, &'a [u8], HttpVersion);
+
+/// A parsed request. The target, header fields and body are slices of the buffer the
+/// request was read into, nothing is copied or decoded before it is asked for.
+///
+/// `body` holds what of a `Content-Length` body came in with the head; the listener reads
+/// the rest, and chunked bodies, as `framing` says.
 pub struct HttpRequest {
-	pub path: String,
 	pub method: HttpMethod,
 	pub version: HttpVersion,
-	pub headers: HashMap<String, String>,
-	pub body: Vec<u8>,
+	pub headers: HeaderMap,
+	pub framing: BodyFraming,
+	pub body: Bytes,
+	pub geo: GeoInfo,
+	target: Bytes,
+	uri: Uri,
+	rest: Bytes,
+	expect_continue: bool,
+	trailers: HeaderMap,
 }
 
 impl HttpRequest {
-	pub async fn new(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+	/// Parses a request head strictly as of RFC 9112: anything a lenient parser would
+	/// have to guess about is rejected with the matching `HttpError`.
+	pub async fn new(bytes: Bytes) -> Result<Self, HttpError> {
 		/*
 		 * CR = 0x0D = /r
 		 * LF = 0x0A = /n
@@ -22,134 +40,547 @@ impl HttpRequest {
 		 * Delimiter = /r/n/r/n
 		 */
 		
-		let mut i: usize = 0;
-		let length = bytes.len(); // MAX_HEADERS_SIZE
+		let Some(head_length) = Self::head_length(&bytes)? else {
+			return Err(Self::incomplete_head_error(&bytes));
+		};
+		
+		// Empty lines before the request line are ignored (RFC 9112 section 2.2)
+		let start = Self::leading_empty_lines(&bytes);
+		let mut lines = split_lines(&bytes[start..head_length - 4]);
 		
 		// 1. Request Line
-		let (method, path, version): (HttpMethod, String, HttpVersion) = {
-			let mut method: Option<HttpMethod> = None;
-			let mut path: Option<String> = None;
-			let mut version: Option<HttpVersion> = None;
-			while i < length - 1 {
-				if bytes[i] == 0x0D && bytes[i + 1] == 0x0A {
-					(method, path, version) = Self::parse_request_line(&bytes[..i])?;
-					break;
-				}
-				
-				i += 1;
-			}
-			
-			// Validate method, path, and version
-			if method.is_none() || path.is_none() || version.is_none() {
-				return Err(Box::new(HttpError::RequestLineNotFound));
+		let (method, target, version) = Self::parse_request_line(lines.next().unwrap_or_default())?;
+		let target = bytes.slice_ref(target);
+		
+		// 2. Headers
+		let mut headers: HeaderMap = HeaderMap::new();
+		for (i, line) in lines.enumerate() {
+			if i >= MAX_HEADERS_LENGTH {
+				return Err(HttpError::HeadersTooLarge);
 			}
 			
-			(method.unwrap(), path.unwrap(), version.unwrap())
-		};
+			let (key, value) = Self::parse_header(line)?;
+			headers.append_slices(bytes.slice_ref(key), bytes.slice_ref(value));
+		}
 		
-		// 4. Headers
-		let mut j = i;
-		let mut eoh = false; // END OF HEADERS
-		let mut length_of_headers = 0;
-		let mut headers: HashMap<String, String> = HashMap::new();
-		while i < length - 3 {
-			if bytes[i] == 0x0D && bytes[i + 1] == 0x0A {
-				if let Some((key, value)) = Self::parse_header(&bytes[j..i]) {
-					headers.insert(key, value);
-				}
-				
-				if bytes[i + 2] == 0x0D && bytes[i + 3] == 0x0A {
-					eoh = true;
-					break;
-				}
-				
-				j = i;
-				i += 2;
-				length_of_headers += 1;
-			} else {
-				i += 1;
-			}
+		let framing = Self::body_framing(&headers, &version)?;
+		let expect_continue = Self::expect_continue(&headers, &version)?;
+		
+		// A single Host, mandatory from HTTP/1.1 clients (RFC 9112 section 3.2)
+		if headers.get_all_bytes("Host").nth(1).is_some() {
+			return Err(HttpError::InvalidHost);
 		}
 		
-		if length_of_headers > MAX_HEADERS_LENGTH || eoh == false {
-			return Err(Box::new(HttpError::HeadersTooLarge));
+		let host = headers.get_all_bytes("Host").next().cloned();
+		
+		if host.is_none() && matches!(version, HttpVersion::Http11) {
+			return Err(HttpError::MissingHost);
 		}
 		
-		// 5. Body
-		i += 4; // CR LF
-		let body: Vec<u8> = if method.has_body() { bytes[i..].to_vec() } else { Vec::new() };
+		// The path is normalized here, before anything can route on it
+		let uri = Uri::parse(&target, host.as_ref())?;
+		
+		// 3. Body
+		let mut rest = bytes.slice(head_length..);
+		let body: Bytes = match framing {
+			BodyFraming::Length(length) => rest.split_to(length.min(rest.len())),
+			BodyFraming::None | BodyFraming::Chunked => Bytes::new(),
+		};
 		
 		Ok(
 			Self {
-				path,
 				method,
 				version,
 				headers,
-				body: body.to_vec(),
+				framing,
+				body,
+				geo: GeoInfo::default(),
+				target,
+				uri,
+				rest,
+				expect_continue,
+				trailers: HeaderMap::new(),
 			}
 		)
 	}
 	
-	pub fn set_body(&mut self, body: Vec<u8>) {
+	/// Length of the head in `bytes` up to and including the empty line ending it, `None`
+	/// while it is still incomplete.
+	pub fn head_length(bytes: &[u8]) -> Result<Option<usize>, HttpError> {
+		let start = Self::leading_empty_lines(bytes);
+		let head_length = bytes[start..].windows(4).position(|window| window == b"\r\n\r\n").map(|end| start + end + 4);
+		
+		// What follows the head is a body or the next pipelined request, its line endings are not ours to check
+		let head = &bytes[..head_length.unwrap_or(bytes.len())];
+		if head.iter().enumerate().any(|(i, byte)| *byte == b'\n' && (i == 0 || head[i - 1] != b'\r')) {
+			return Err(HttpError::InvalidLineEnding);
+		}
+		
+		Ok(head_length)
+	}
+	
+	/// Request target as sent, query included.
+	pub fn target(&self) -> &str {
+		// Only visible ASCII gets past parsing
+		std::str::from_utf8(&self.target).unwrap_or_default()
+	}
+	
+	pub fn uri(&self) -> &Uri {
+		&self.uri
+	}
+	
+	/// Decoded and normalized path of the target, see `PathNormalizer`. The query is split
+	/// off before decoding, so an escaped `?` stays part of the path.
+	pub fn path(&self) -> &str {
+		self.uri.path()
+	}
+	
+	/// Parameters of the query string, parsed on each call.
+	pub fn query(&self) -> Query {
+		Query::parse(self.uri.query().unwrap_or_default())
+	}
+	
+	pub fn query_as<T: FromQuery>(&self) -> Result<T, QueryError> {
+		self.query().deserialize()
+	}
+	
+	/// The request as a TRACE response echoes it (RFC 9110 section 9.3.8), minus the
+	/// fields holding credentials.
+	pub fn trace_message(&self) -> String {
+		let mut headers = self.headers.clone();
+		for sensitive in ["Authorization", "Proxy-Authorization", "Cookie"] {
+			headers.remove(sensitive);
+		}
+		
+		format!("{} {} {}\r\n{}\r\n", self.method.as_str(), self.target(), self.version.as_str(), headers)
+	}
+	
+	pub fn set_body(&mut self, body: Bytes) {
 		self.body = body;
 	}
 	
-	pub fn content_length(&self) -> usize {
-		self.headers.get("Content-Length").unwrap_or(&String::from("0")).parse::<usize>().unwrap_or(0)
+	/// Whether the client waits for `100 Continue` before sending its body.
+	pub fn expects_continue(&self) -> bool {
+		self.expect_continue && self.framing != BodyFraming::None
 	}
 	
-	fn parse_request_line(as_bytes: &[u8]) -> Result<(Option<HttpMethod>, Option<String>, Option<HttpVersion>), Box<dyn Error>> {
-		if let Ok(str) = std::str::from_utf8(as_bytes) {
-			let mut parts = str.splitn(3, ' ');
-			
-			let method: HttpMethod;
-			if let Some(v) = parts.next() {
-				method = HttpMethod::from_str(v);
-				if method.is_supported() == false {
-					return Err(Box::new(HttpError::UnsupportedMethod));
+	/// Bytes read past the head that are not part of `body`: the start of a chunked body,
+	/// then, once the body is read, of the next pipelined request.
+	pub fn take_rest(&mut self) -> Bytes {
+		std::mem::take(&mut self.rest)
+	}
+	
+	pub fn set_rest(&mut self, rest: Bytes) {
+		self.rest = rest;
+	}
+	
+	/// Trailer fields of a chunked body, empty until the body has been read.
+	pub fn trailers(&self) -> &HeaderMap {
+		&self.trailers
+	}
+	
+	pub fn set_trailers(&mut self, trailers: HeaderMap) {
+		self.trailers = trailers;
+	}
+	
+	fn leading_empty_lines(bytes: &[u8]) -> usize {
+		let mut start: usize = 0;
+		while bytes[start..].starts_with(b"\r\n") {
+			start += 2;
+		}
+		
+		start
+	}
+	
+	/// Tells a head cut short by the size limit from one that is just not all there.
+	fn incomplete_head_error(bytes: &[u8]) -> HttpError {
+		let start = Self::leading_empty_lines(bytes);
+		let request_line_ended = bytes[start..].windows(2).any(|window| window == b"\r\n");
+		
+		match (request_line_ended, bytes.len() >= MAX_HEADERS_SIZE) {
+			(false, true) => HttpError::UriTooLong,
+			(false, false) => HttpError::RequestLineNotFound,
+			(true, true) => HttpError::HeadersTooLarge,
+			(true, false) => HttpError::IncompleteHead,
+		}
+	}
+	
+	/*
+	 * request-line = method SP request-target SP HTTP-version
+	 * Exactly one space between the parts, nothing around them.
+	 */
+	fn parse_request_line(line: &[u8]) -> Result<RequestLine<'_>, HttpError> {
+		let line = std::str::from_utf8(line).map_err(|_| HttpError::InvalidRequestLine)?;
+		let mut parts = line.split(' ');
+		
+		let method = parts.next().filter(|method| !method.is_empty()).ok_or(HttpError::MethodNotFound)?;
+		let target = parts.next().ok_or(HttpError::UrlNotFound)?;
+		let version = parts.next().ok_or(HttpError::VersionNotFound)?;
+		if parts.next().is_some() || target.is_empty() {
+			return Err(HttpError::InvalidRequestLine);
+		}
+		
+		if !method.bytes().all(is_token_char) {
+			return Err(HttpError::InvalidMethod);
+		}
+		
+		if target.len() > MAX_REQUEST_TARGET_LENGTH {
+			return Err(HttpError::UriTooLong);
+		}
+		
+		if target.bytes().any(|byte| byte <= b' ' || byte >= 0x7F) {
+			return Err(HttpError::InvalidRequestTarget);
+		}
+		
+		// CONNECT takes an authority-form target and nothing else does, `*` is only for OPTIONS
+		let method = HttpMethod::from(method);
+		let valid_form = match TargetForm::of(target) {
+			TargetForm::Authority => method == HttpMethod::Connect,
+			TargetForm::Asterisk => method == HttpMethod::Options,
+			TargetForm::Origin | TargetForm::Absolute => method != HttpMethod::Connect,
+		};
+		
+		if !valid_form {
+			return Err(HttpError::InvalidRequestTarget);
+		}
+		
+		// HTTP-version = "HTTP/" DIGIT "." DIGIT
+		let version_bytes = version.as_bytes();
+		if !(version_bytes.len() == 8 && version.starts_with("HTTP/") && version_bytes[5].is_ascii_digit() && version_bytes[6] == b'.' && version_bytes[7].is_ascii_digit()) {
+			return Err(HttpError::InvalidVersion);
+		}
+		
+		let version = HttpVersion::from(version);
+		if !version.is_supported() {
+			return Err(HttpError::UnsupportedVersion);
+		}
+		
+		Ok((method, target.as_bytes(), version))
+	}
+	
+	/*
+	 * field-line = field-name ":" OWS field-value OWS
+	 * No whitespace before the colon, no folding onto the next line.
+	 */
+	pub(crate) fn parse_header(line: &[u8]) -> Result<(&[u8], &[u8]), HttpError> {
+		if line.first().is_some_and(|byte| *byte == b' ' || *byte == b'\t') {
+			return Err(HttpError::ObsoleteLineFolding);
+		}
+		
+		let colon = line.iter().position(|byte| *byte == b':').ok_or(HttpError::HeaderWithoutColon)?;
+		let (name, value) = (&line[..colon], &line[colon + 1..]);
+		
+		if name.last().is_some_and(|byte| *byte == b' ' || *byte == b'\t') {
+			return Err(HttpError::WhitespaceBeforeColon);
+		}
+		
+		if name.is_empty() || !name.iter().copied().all(is_token_char) {
+			return Err(HttpError::InvalidHeaderName);
+		}
+		
+		let value = value.trim_ascii();
+		if value.iter().any(|byte| (*byte < b' ' && *byte != b'\t') || *byte == 0x7F) {
+			return Err(HttpError::InvalidHeaderValue);
+		}
+		
+		Ok((name, value))
+	}
+	
+	/*
+	 * RFC 9112 section 6.3: a body is framed by Transfer-Encoding ending in chunked, else by
+	 * Content-Length, else there is none. Both at once is how requests get smuggled past a
+	 * proxy reading them the other way, so it is refused rather than resolved.
+	 */
+	fn body_framing(headers: &HeaderMap, version: &HttpVersion) -> Result<BodyFraming, HttpError> {
+		let content_length = Self::content_length(headers)?;
+		if !headers.contains_key("Transfer-Encoding") {
+			return Ok(content_length.filter(|length| *length > 0).map_or(BodyFraming::None, BodyFraming::Length));
+		}
+		
+		if content_length.is_some() {
+			return Err(HttpError::ConflictingFraming);
+		}
+		
+		// HTTP/1.0 has no transfer codings, a 1.1 proxy may have passed one on anyway
+		if matches!(version, HttpVersion::Http10) {
+			return Err(HttpError::InvalidTransferEncoding);
+		}
+		
+		let codings: Vec<&str> = headers.get_all("Transfer-Encoding").flat_map(|value| value.split(',')).map(str::trim).collect();
+		match codings.split_last() {
+			Some((last, others)) if last.eq_ignore_ascii_case("chunked") => {
+				if others.iter().any(|coding| coding.eq_ignore_ascii_case("chunked")) {
+					return Err(HttpError::InvalidTransferEncoding);
+				}
+				
+				// Compressed request bodies are not decoded
+				if !others.is_empty() {
+					return Err(HttpError::NotImplemented);
 				}
-			} else {
-				return Err(Box::new(HttpError::MethodNotFound));
+				
+				Ok(BodyFraming::Chunked)
+			},
+			_ => Err(HttpError::InvalidTransferEncoding),
+		}
+	}
+	
+	/*
+	 * `100-continue` is the only expectation there is (RFC 9110 section 10.1.1), anything
+	 * else cannot be met. HTTP/1.0 clients predate the field and a 1.0 proxy may have
+	 * forwarded it without knowing, so it is ignored there.
+	 */
+	fn expect_continue(headers: &HeaderMap, version: &HttpVersion) -> Result<bool, HttpError> {
+		if matches!(version, HttpVersion::Http10) {
+			return Ok(false);
+		}
+		
+		let mut expect_continue = false;
+		for expectation in headers.get_all("Expect").flat_map(|value| value.split(',')).map(str::trim) {
+			if !expectation.eq_ignore_ascii_case("100-continue") {
+				return Err(HttpError::ExpectationFailed);
 			}
 			
-			let path: String;
-			if let Some(v) = parts.next() {
-				if let Ok(decoded_path) = urlencoding::decode(v) {
-					path = decoded_path.to_string();
-				} else {
-					return Err(Box::new(HttpError::DecodeUrlFailed));
-				}
-			} else {
-				return Err(Box::new(HttpError::UrlNotFound));
+			expect_continue = true;
+		}
+		
+		Ok(expect_continue)
+	}
+	
+	/// Several `Content-Length` values are only tolerated when they all agree (RFC 9110
+	/// section 8.6), anything else could be read differently by another hop.
+	fn content_length(headers: &HeaderMap) -> Result<Option<usize>, HttpError> {
+		let mut content_length: Option<usize> = None;
+		for value in headers.get_all("Content-Length").flat_map(|value| value.split(',')) {
+			let value = value.trim();
+			if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+				return Err(HttpError::InvalidContentLength);
 			}
 			
-			let version: HttpVersion;
-			if let Some(v) = parts.next() {
-				version = HttpVersion::from_str(v);
-				if version.is_supported() == false {
-					return Err(Box::new(HttpError::UnsupportedVersion));
-				}
-			} else {
-				return Err(Box::new(HttpError::VersionNotFound));
+			let value: usize = value.parse().map_err(|_| HttpError::InvalidContentLength)?;
+			if content_length.is_some_and(|content_length| content_length != value) {
+				return Err(HttpError::InvalidContentLength);
 			}
 			
-			return Ok((Some(method), Some(path), Some(version)));
+			content_length = Some(value);
 		}
 		
-		Ok((None, None, None))
+		Ok(content_length)
+	}
+}
+
+fn split_lines(head: &[u8]) -> impl Iterator<Item = &[u8]> {
+	let mut rest: Option<&[u8]> = Some(head);
+	std::iter::from_fn(move || {
+		let line = rest?;
+		match line.windows(2).position(|window| window == b"\r\n") {
+			Some(end) => {
+				rest = Some(&line[end + 2..]);
+				Some(&line[..end])
+			},
+			None => rest.take(),
+		}
+	})
+}
+
+// tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*" / "+" / "-" / "." / "^" / "_" / "`" / "|" / "~" / DIGIT / ALPHA
+fn is_token_char(byte: u8) -> bool {
+	byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const ACCEPTED: &[&str] = &[
+		"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		"GET / HTTP/1.0\r\n\r\n",
+		"\r\n\r\nGET / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		"GET /a%20b?x=1&y=2 HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		"GET / HTTP/1.1\r\nHost:example.com\r\n\r\n",
+		"GET / HTTP/1.1\r\nHost: \t example.com \t \r\n\r\n",
+		"GET / HTTP/1.1\r\nHost: example.com\r\nX-Empty:\r\n\r\n",
+		"GET / HTTP/1.1\r\nHost: example.com\r\nUser-Agent: a\tb\r\n\r\n",
+		"GET / HTTP/1.1\r\nHost: example.com\r\nX-Custom_Name.v2: !#$%&'*+-.^_`|~\r\n\r\n",
+		"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\n\r\nbody",
+		"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\nContent-Length: 4\r\n\r\nbody",
+		"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4, 4\r\n\r\nbody",
+		"BREW / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		"get / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		"POST / HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: Chunked\r\n\r\n",
+	];
+	
+	const REJECTED: &[(&str, HttpError)] = &[
+		("GET / HTTP/1.1\nHost: example.com\n\n", HttpError::InvalidLineEnding),
+		("GET / HTTP/1.1\r\nHost: example.com\n\r\n", HttpError::InvalidLineEnding),
+		("GET / HTTP/1.1\rHost: example.com\r\n\r\n", HttpError::InvalidRequestLine),
+		("GET / HTTP/1.1\r\nHost: example.com\r\n", HttpError::IncompleteHead),
+		("GET / HTTP/1.1", HttpError::RequestLineNotFound),
+		(" / HTTP/1.1\r\n\r\n", HttpError::MethodNotFound),
+		("GET\r\n\r\n", HttpError::UrlNotFound),
+		("GET /\r\n\r\n", HttpError::VersionNotFound),
+		("GET  / HTTP/1.1\r\n\r\n", HttpError::InvalidRequestLine),
+		("GET / HTTP/1.1 \r\n\r\n", HttpError::InvalidRequestLine),
+		("GET /a b HTTP/1.1\r\n\r\n", HttpError::InvalidRequestLine),
+		("G(T / HTTP/1.1\r\n\r\n", HttpError::InvalidMethod),
+		("GET /\x01 HTTP/1.1\r\n\r\n", HttpError::InvalidRequestTarget),
+		("GET /\u{e9} HTTP/1.1\r\n\r\n", HttpError::InvalidRequestTarget),
+		("GET / HTTP/1.1.1\r\n\r\n", HttpError::InvalidVersion),
+		("GET / http/1.1\r\n\r\n", HttpError::InvalidVersion),
+		("GET / HTTP/2\r\n\r\n", HttpError::InvalidVersion),
+		("GET / HTTP/3.0\r\n\r\n", HttpError::UnsupportedVersion),
+		("GET / HTTP/1.1\r\nHost example.com\r\n\r\n", HttpError::HeaderWithoutColon),
+		("GET / HTTP/1.1\r\nHost : example.com\r\n\r\n", HttpError::WhitespaceBeforeColon),
+		("GET / HTTP/1.1\r\n: example.com\r\n\r\n", HttpError::InvalidHeaderName),
+		("GET / HTTP/1.1\r\nHo(st: example.com\r\n\r\n", HttpError::InvalidHeaderName),
+		("GET / HTTP/1.1\r\nHost: exa\x00mple.com\r\n\r\n", HttpError::InvalidHeaderValue),
+		("GET / HTTP/1.1\r\nHost: exa\rmple.com\r\n\r\n", HttpError::InvalidHeaderValue),
+		("GET / HTTP/1.1\r\nX-Folded: a\r\n b\r\n\r\n", HttpError::ObsoleteLineFolding),
+		("GET / HTTP/1.1\r\n Host: example.com\r\n\r\n", HttpError::ObsoleteLineFolding),
+		("POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length: +4\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length: 4 4\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length:\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 5\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length: 4, 5\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n", HttpError::InvalidContentLength),
+		("POST / HTTP/1.1\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n", HttpError::ConflictingFraming),
+		("POST / HTTP/1.1\r\nTransfer-Encoding: chunked, identity\r\n\r\n", HttpError::InvalidTransferEncoding),
+		("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: chunked\r\n\r\n", HttpError::InvalidTransferEncoding),
+		("POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n", HttpError::InvalidTransferEncoding),
+		("POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n", HttpError::NotImplemented),
+		("POST / HTTP/1.1\r\nHost: example.com\r\nExpect: 200-ok\r\n\r\n", HttpError::ExpectationFailed),
+	];
+	
+	#[tokio::test]
+	async fn accepts_well_formed_requests() {
+		for raw in ACCEPTED {
+			if let Err(e) = HttpRequest::new(Bytes::from_static(raw.as_bytes())).await {
+				panic!("{:?} was rejected with {:?}", raw, e);
+			}
+		}
 	}
 	
-	fn parse_header(as_bytes: &[u8]) -> Option<(String, String)> {
-		if let Ok(header) = std::str::from_utf8(&as_bytes) {
-			let mut parts = header.splitn(2, ':');
-			
-			if let Some(key) = parts.next() {
-				if let Some(value) = parts.next() {
-					return Some((String::from(key.trim()), String::from(sanitize_header_value(value.trim()))));
-				}
+	#[tokio::test]
+	async fn rejects_malformed_requests() {
+		for (raw, expected) in REJECTED {
+			match HttpRequest::new(Bytes::from_static(raw.as_bytes())).await {
+				Ok(_) => panic!("{:?} was accepted", raw),
+				Err(e) => assert_eq!(&e, expected, "{:?}", raw),
 			}
 		}
+	}
+	
+	#[tokio::test]
+	async fn rejects_oversized_heads() {
+		let target = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_REQUEST_TARGET_LENGTH));
+		assert_eq!(HttpRequest::new(Bytes::from(target)).await.err(), Some(HttpError::UriTooLong));
 		
-		None
+		let unterminated = format!("GET /{}", "a".repeat(MAX_HEADERS_SIZE));
+		assert_eq!(HttpRequest::new(Bytes::from(unterminated)).await.err(), Some(HttpError::UriTooLong));
+		
+		let fields = "X-Field: value\r\n".repeat(MAX_HEADERS_LENGTH + 1);
+		let many = format!("GET / HTTP/1.1\r\n{}\r\n", fields);
+		assert_eq!(HttpRequest::new(Bytes::from(many)).await.err(), Some(HttpError::HeadersTooLarge));
+		
+		let cut = format!("GET / HTTP/1.1\r\nX-Field: {}", "a".repeat(MAX_HEADERS_SIZE));
+		assert_eq!(HttpRequest::new(Bytes::from(cut)).await.err(), Some(HttpError::HeadersTooLarge));
 	}
-}
\ No newline at end of file
+	
+	#[tokio::test]
+	async fn parses_fields() {
+		let req = HttpRequest::new(Bytes::from_static(b"\r\nPOST /a%20b HTTP/1.1\r\nhost:  example.com \r\nX-Tag: a\r\nx-tag: b\r\nContent-Length: 4\r\n\r\nbody")).await.unwrap();
+		assert_eq!(req.method, HttpMethod::Post);
+		assert_eq!(req.target(), "/a%20b");
+		assert_eq!(req.path(), "/a b");
+		assert_eq!(req.headers.get("Host"), Some("example.com"));
+		assert_eq!(req.headers.get_all("X-Tag").collect::<Vec<&str>>(), ["a", "b"]);
+		assert_eq!(req.framing, BodyFraming::Length(4));
+		assert_eq!(req.body, &b"body"[..]);
+	}
+	
+	#[tokio::test]
+	async fn reads_expectations() {
+		let expectations = [
+			("POST / HTTP/1.1\r\nHost: example.com\r\nExpect: 100-Continue\r\nContent-Length: 4\r\n\r\n", true),
+			("POST / HTTP/1.1\r\nHost: example.com\r\nExpect: 100-continue\r\nTransfer-Encoding: chunked\r\n\r\n", true),
+			("POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\n\r\n", false),
+			("GET / HTTP/1.1\r\nHost: example.com\r\nExpect: 100-continue\r\n\r\n", false),
+			("POST / HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 4\r\n\r\n", false),
+			("POST / HTTP/1.0\r\nExpect: 200-ok\r\nContent-Length: 4\r\n\r\n", false),
+		];
+		
+		for (raw, expected) in expectations {
+			let req = HttpRequest::new(Bytes::from_static(raw.as_bytes())).await.unwrap();
+			assert_eq!(req.expects_continue(), expected, "{:?}", raw);
+		}
+	}
+	
+	#[tokio::test]
+	async fn keeps_pipelined_bytes() {
+		let pipelined = Bytes::from_static(b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\na\nb\ncGET /next HTTP/1.1\r\nHost: example.com\r\n\r\nGET");
+		let mut req = HttpRequest::new(pipelined).await.unwrap();
+		assert_eq!(req.body, &b"a\nb\nc"[..]);
+		
+		let mut next = HttpRequest::new(req.take_rest()).await.unwrap();
+		assert_eq!(next.path(), "/next");
+		assert_eq!(next.take_rest(), &b"GET"[..]);
+		assert_eq!(HttpRequest::head_length(b"GET"), Ok(None));
+	}
+	
+	#[tokio::test]
+	async fn frames_bodies() {
+		let req = HttpRequest::new(Bytes::from_static(b"PROPFIND /dav HTTP/1.1\r\nHost: example.com\r\nContent-Length: 0\r\n\r\n")).await.unwrap();
+		assert_eq!(req.method, HttpMethod::Extension(String::from("PROPFIND")));
+		assert!(!req.method.is_implemented());
+		assert_eq!(req.framing, BodyFraming::None);
+		
+		// Chunks are left to the listener, which reads them with a ChunkedDecoder
+		let mut req = HttpRequest::new(Bytes::from_static(b"POST / HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nWiki\r\n")).await.unwrap();
+		assert_eq!(req.framing, BodyFraming::Chunked);
+		assert!(req.body.is_empty());
+		assert_eq!(req.take_rest(), &b"4;ext=1\r\nWiki\r\n"[..]);
+	}
+	
+	#[tokio::test]
+	async fn splits_query_from_path() {
+		let req = HttpRequest::new(Bytes::from_static(b"GET /a%3Fb/c?x=1+2&x=%26&flag&n=5&name=caf%C3%A9 HTTP/1.1\r\nHost: example.com\r\n\r\n")).await.unwrap();
+		assert_eq!(req.target(), "/a%3Fb/c?x=1+2&x=%26&flag&n=5&name=caf%C3%A9");
+		assert_eq!(req.path(), "/a%3Fb/c");
+		
+		assert_eq!(req.query().get_all("x").collect::<Vec<&str>>(), ["1 2", "&"]);
+	}
+	
+	#[tokio::test]
+	async fn normalizes_the_path_of_the_target() {
+		let req = HttpRequest::new(Bytes::from_static(b"GET /static/%2E%2E/a%20b?x=.. HTTP/1.1\r\nHost: example.com\r\n\r\n")).await.unwrap();
+		assert_eq!((req.path(), req.uri().query()), ("/a b", Some("x=..")));
+		
+		let traversal = Bytes::from_static(b"GET /static/..%2F..%2Fetc/passwd HTTP/1.1\r\nHost: example.com\r\n\r\n");
+		assert_eq!(HttpRequest::new(traversal).await.err(), Some(HttpError::EncodedSlash));
+	}
+	
+	#[tokio::test]
+	async fn parses_target_forms() {
+		let req = HttpRequest::new(Bytes::from_static(b"GET http://Example.com:8080/a/../b?x=1 HTTP/1.1\r\nHost: ignored.example\r\n\r\n")).await.unwrap();
+		assert_eq!((req.uri().form(), req.uri().authority(), req.path()), (TargetForm::Absolute, Some("Example.com:8080"), "/b"));
+		
+		let req = HttpRequest::new(Bytes::from_static(b"GET /a HTTP/1.0\r\n\r\n")).await.unwrap();
+		assert_eq!(req.uri().authority(), None);
+		
+		let req = HttpRequest::new(Bytes::from_static(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n")).await.unwrap();
+		assert_eq!(req.uri().form(), TargetForm::Authority);
+		
+		let rejected: [(&[u8], HttpError); 6] = [
+			(b"GET / HTTP/1.1\r\n\r\n", HttpError::MissingHost),
+			(b"GET / HTTP/1.1\r\nHost: a.example\r\nHost: b.example\r\n\r\n", HttpError::InvalidHost),
+			(b"GET / HTTP/1.1\r\nHost: exa mple.com\r\n\r\n", HttpError::InvalidHost),
+			(b"GET example.com:443 HTTP/1.1\r\nHost: example.com\r\n\r\n", HttpError::InvalidRequestTarget),
+			(b"GET * HTTP/1.1\r\nHost: example.com\r\n\r\n", HttpError::InvalidRequestTarget),
+			(b"CONNECT / HTTP/1.1\r\nHost: example.com\r\n\r\n", HttpError::InvalidRequestTarget),
+		];
+		for (raw, error) in rejected {
+			assert_eq!(HttpRequest::new(Bytes::from_static(raw)).await.err(), Some(error), "{:?}", String::from_utf8_lossy(raw));
+		}
+	}
+}
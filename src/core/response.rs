@@ -1,10 +1,14 @@
 use std::error::Error;
+use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
+use tokio::time::Instant;
 use crate::core::HttpRequest;
 use crate::enums::{HttpError, HttpVersion};
 use crate::protocols::{HttpV10, HttpV11};
 use crate::traits::HttpProtocol;
+use crate::utils::data_rate::write_all_with_min_rate;
+use crate::RESPONSE_WRITE_TIMEOUT_SECS;
 
 pub struct HttpResponse {
 	pub keep_connection_alive: bool,
@@ -21,7 +25,7 @@ impl HttpResponse {
 				(false, body)
 			},
 			HttpVersion::Http11 => {
-				let keep_alive = req.headers.get("Connection").map(|v| v.eq_ignore_ascii_case("keep-alive")).unwrap_or(false);
+				let keep_alive = HttpV11::keep_alive(&req.headers);
 				let body = HttpV11::handle(req).await?;
 				(keep_alive, body)
 			},
@@ -36,10 +40,13 @@ impl HttpResponse {
 		)
 	}
 	
-	pub async fn send(self, mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
-		stream.write_all(&self.body).await?;
+	/// Writes the response, shutting the stream down unless the connection stays open for
+	/// the next request.
+	pub async fn send(&self, stream: &mut TcpStream) -> Result<(), Box<dyn Error>> {
+		let deadline: Instant = Instant::now() + Duration::from_secs(RESPONSE_WRITE_TIMEOUT_SECS);
+		write_all_with_min_rate(stream, &self.body, deadline).await.map_err(|e| e as Box<dyn Error>)?;
 		
-		if self.keep_connection_alive == false {
+		if !self.keep_connection_alive {
 			stream.shutdown().await?;
 		}
 		
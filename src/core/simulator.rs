@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{DateTime, FixedOffset};
+use tokio::{
+	fs::File,
+	io::{AsyncBufReadExt, BufReader}
+};
+use crate::core::{ConnectionInfo, GeoIp, IpFilter, RateLimiter};
+use crate::enums::RateLimitDecision;
+use crate::stores::MemoryStore;
+use crate::traits::{RateLimitStore, StoreBan, StoreFuture};
+use crate::{POW_CHALLENGE_THRESHOLD, TARPIT_THRESHOLD};
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(5);
+const REPORT_TOP_ENTRIES: usize = 10;
+
+struct LogEntry<'a> {
+	time: DateTime<FixedOffset>,
+	ip: IpAddr,
+	method: &'a str,
+	path: &'a str,
+}
+
+#[derive(Default)]
+struct PolicyOutcome {
+	requests: u64,
+	clients: HashMap<IpAddr, u64>,
+}
+
+/// What the configured policies would have done to the requests of an access log.
+pub struct SimulationReport {
+	limit: u8,
+	requests: u64,
+	clients: HashSet<IpAddr>,
+	allowlisted: u64,
+	skipped_lines: u64,
+	first: Option<DateTime<FixedOffset>>,
+	last: Option<DateTime<FixedOffset>>,
+	policies: BTreeMap<String, PolicyOutcome>,
+	blocked_routes: HashMap<String, u64>,
+}
+
+/// `MemoryStore` read on the virtual clock of a replay, so `RateLimiter::check_at` counts
+/// logged requests at the time they were made.
+struct ReplayStore<'a> {
+	store: &'a MemoryStore,
+	now: Instant,
+}
+
+/// Replays access logs through the rate limiting policies on a virtual clock driven by
+/// the log timestamps, so a day of traffic is simulated in seconds.
+///
+/// Every request goes through the same steps as a live one: IP and geo deny rules,
+/// the allowlist, geo rate limits and finally the per-client limit, with the tarpit
+/// and proof-of-work thresholds. Logs carry neither cookies nor TLS fingerprints, so
+/// clients are never verified and fingerprint policies are not simulated.
+pub struct Simulator;
+
+impl Simulator {
+	/// Replays the log at `path` with `limit` in place of `MAX_REQUEST_PER_MINUTE`.
+	pub async fn replay(path: &str, limit: u8) -> Result<SimulationReport, IoError> {
+		let mut lines = BufReader::new(File::open(path).await?).lines();
+		let store = MemoryStore::new();
+		let origin = Instant::now();
+		let mut clock = origin;
+		let mut last_cleanup = origin;
+		let mut report = SimulationReport {
+			limit,
+			requests: 0,
+			clients: HashSet::new(),
+			allowlisted: 0,
+			skipped_lines: 0,
+			first: None,
+			last: None,
+			policies: BTreeMap::new(),
+			blocked_routes: HashMap::new(),
+		};
+		
+		while let Some(line) = lines.next_line().await? {
+			let Some(entry) = parse_line(&line) else {
+				report.skipped_lines += 1;
+				continue;
+			};
+			
+			// Out of order lines never move the clock backwards
+			let first = *report.first.get_or_insert(entry.time);
+			clock = clock.max(origin + (entry.time - first).to_std().unwrap_or_default());
+			report.last = Some(report.last.map_or(entry.time, |last| last.max(entry.time)));
+			report.requests += 1;
+			report.clients.insert(entry.ip);
+			
+			if clock.duration_since(last_cleanup) >= CLEANUP_INTERVAL {
+				store.cleanup_at(clock);
+				last_cleanup = clock;
+			}
+			
+			if let Some(policy) = Self::evaluate(&ReplayStore { store: &store, now: clock }, &entry, limit).await {
+				report.record(policy, &entry);
+			} else if IpFilter::is_allowed(entry.ip) {
+				report.allowlisted += 1;
+			}
+		}
+		
+		Ok(report)
+	}
+	
+	/// Name of the policy answering `entry` with anything but a plain response.
+	async fn evaluate(store: &ReplayStore<'_>, entry: &LogEntry<'_>, limit: u8) -> Option<String> {
+		// Decided on accept, before the rate limiter sees the request
+		if IpFilter::is_denied(entry.ip) {
+			return Some(String::from("denylist"));
+		}
+		
+		let geo = GeoIp::lookup(entry.ip);
+		if GeoIp::is_denied(&geo) {
+			return Some(String::from("geo deny"));
+		}
+		
+		let info = ConnectionInfo { addr: SocketAddr::new(entry.ip, 0), geo, tls_fingerprint: None };
+		match RateLimiter::check_at(store, &info, limit, false).await {
+			(_, Some(shared_limit)) => Some(format!("{} ({}/min)", shared_limit.key, shared_limit.limit)),
+			(RateLimitDecision::Allow, None) => None,
+			(RateLimitDecision::Reject, None) => Some(format!("per-client limit ({}/min)", limit)),
+			(RateLimitDecision::Tarpit, None) => Some(format!("tarpit (from {}/min)", TARPIT_THRESHOLD.unwrap_or(0))),
+			(RateLimitDecision::Challenge, None) => Some(format!("proof-of-work challenge (from {}/min)", POW_CHALLENGE_THRESHOLD.unwrap_or(0))),
+		}
+	}
+}
+
+impl RateLimitStore for ReplayStore<'_> {
+	fn add<'a>(&'a self, key: &'a str, hits: u8) -> StoreFuture<'a, u8> {
+		Box::pin(async move { Ok(self.store.add_at(key, hits, self.now)) })
+	}
+	
+	fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<u8>> {
+		Box::pin(async move { Ok(self.store.get_at(key, self.now)) })
+	}
+	
+	fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.store.remove_sync(key);
+			Ok(())
+		})
+	}
+	
+	fn cleanup(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			self.store.cleanup_at(self.now);
+			Ok(())
+		})
+	}
+	
+	// Logs carry no bans, these only keep the store whole
+	fn ban<'a>(&'a self, key: &'a str, until: SystemTime) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.store.ban_sync(key, until);
+			Ok(())
+		})
+	}
+	
+	fn unban<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool> {
+		Box::pin(async move { Ok(self.store.unban_sync(key)) })
+	}
+	
+	fn ban_ends<'a>(&'a self, keys: &'a [String]) -> StoreFuture<'a, Vec<Option<SystemTime>>> {
+		Box::pin(async move { Ok(self.store.ban_ends_sync(keys)) })
+	}
+	
+	fn bans(&self) -> StoreFuture<'_, Vec<StoreBan>> {
+		Box::pin(async move { Ok(self.store.bans_sync()) })
+	}
+}
+
+impl SimulationReport {
+	fn record(&mut self, policy: String, entry: &LogEntry) {
+		let outcome = self.policies.entry(policy).or_default();
+		outcome.requests += 1;
+		*outcome.clients.entry(entry.ip).or_insert(0) += 1;
+		*self.blocked_routes.entry(format!("{} {}", entry.method, entry.path)).or_insert(0) += 1;
+	}
+}
+
+impl Display for SimulationReport {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		writeln!(f, "Replayed {} requests from {} clients with a limit of {} requests per minute", self.requests, self.clients.len(), self.limit)?;
+		if let (Some(first), Some(last)) = (self.first, self.last) {
+			writeln!(f, "Log spans {} to {}", first.to_rfc3339(), last.to_rfc3339())?;
+		}
+		
+		writeln!(f, "Skipped {} unparseable lines, {} allowlisted requests", self.skipped_lines, self.allowlisted)?;
+		
+		if self.policies.is_empty() {
+			return writeln!(f, "\nNo request would have been blocked");
+		}
+		
+		for (policy, outcome) in &self.policies {
+			writeln!(f, "\n{}: {} requests from {} clients", policy, outcome.requests, outcome.clients.len())?;
+			for (ip, requests) in top_entries(&outcome.clients) {
+				writeln!(f, "  {:<40} {}", ip, requests)?;
+			}
+		}
+		
+		writeln!(f, "\nMost affected routes:")?;
+		for (route, requests) in top_entries(&self.blocked_routes) {
+			writeln!(f, "  {:<40} {}", route, requests)?;
+		}
+		
+		Ok(())
+	}
+}
+
+fn top_entries<K: Display + Ord>(counts: &HashMap<K, u64>) -> Vec<(&K, u64)> {
+	let mut entries: Vec<(&K, u64)> = counts.iter().map(|(key, count)| (key, *count)).collect();
+	entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+	entries.truncate(REPORT_TOP_ENTRIES);
+	
+	entries
+}
+
+/*
+ * Two layouts are understood:
+ * <ip> <ident> <user> [10/Oct/2000:13:55:36 -0700] "GET /path HTTP/1.1" ... (common and combined log formats)
+ * <RFC 3339 timestamp> <ip> <method> <path>
+ */
+fn parse_line(line: &str) -> Option<LogEntry<'_>> {
+	if let Some((client, rest)) = line.split_once(" [") {
+		let (time, rest) = rest.split_once("] \"")?;
+		let mut request = rest.split('"').next()?.split(' ');
+		
+		return Some(LogEntry {
+			time: DateTime::parse_from_str(time, "%d/%b/%Y:%H:%M:%S %z").ok()?,
+			ip: client.split(' ').next()?.parse().ok()?,
+			method: request.next()?,
+			path: request.next()?,
+		});
+	}
+	
+	let mut fields = line.split_whitespace();
+	Some(LogEntry {
+		time: DateTime::parse_from_rfc3339(fields.next()?).ok()?,
+		ip: fields.next()?.parse().ok()?,
+		method: fields.next()?,
+		path: fields.next()?,
+	})
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn parses_common_and_combined_logs() {
+		let common = parse_line(r#"192.0.2.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#).unwrap();
+		assert_eq!(common.ip, "192.0.2.1".parse::<IpAddr>().unwrap());
+		assert_eq!(common.time, DateTime::parse_from_rfc3339("2000-10-10T13:55:36-07:00").unwrap());
+		assert_eq!((common.method, common.path), ("GET", "/apache_pb.gif"));
+		
+		let combined = parse_line(r#"2001:db8::1 - - [01/Feb/2024:08:00:00 +0000] "POST /login?next=%2F HTTP/1.1" 302 0 "https://example.com/" "curl/8.0""#).unwrap();
+		assert_eq!(combined.ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+		assert_eq!((combined.method, combined.path), ("POST", "/login?next=%2F"));
+	}
+	
+	#[test]
+	fn parses_timestamped_logs() {
+		let entry = parse_line("2024-02-01T08:00:00.250Z 198.51.100.7 DELETE /api/items/3").unwrap();
+		assert_eq!(entry.ip, "198.51.100.7".parse::<IpAddr>().unwrap());
+		assert_eq!(entry.time, DateTime::parse_from_rfc3339("2024-02-01T08:00:00.250+00:00").unwrap());
+		assert_eq!((entry.method, entry.path), ("DELETE", "/api/items/3"));
+	}
+	
+	#[test]
+	fn skips_malformed_lines() {
+		for line in [
+			"",
+			"not a log line",
+			r#"192.0.2.1 - - [10/Oct/2000:13:55:36] "GET / HTTP/1.0" 200 1"#,
+			r#"192.0.2.1 - - [10/Oct/2000:13:55:36 -0700] "-" 400 0"#,
+			r#"host.example - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 1"#,
+			"2024-02-01 08:00:00 198.51.100.7 GET /",
+			"2024-02-01T08:00:00Z 198.51.100.7 GET",
+			"2024-02-01T08:00:00Z 198.51.100 GET /",
+		] {
+			assert!(parse_line(line).is_none(), "parsed {:?}", line);
+		}
+	}
+	
+	#[tokio::test]
+	async fn counts_what_each_policy_would_have_blocked() {
+		let log = [
+			"2024-02-01T08:00:00Z 192.0.2.1 GET /",
+			"2024-02-01T08:00:10Z 192.0.2.1 GET /",
+			"2024-02-01T08:00:20Z 192.0.2.1 GET /search",
+			"2024-02-01T08:00:30Z 192.0.2.2 GET /",
+			"2024-02-01T08:00:40Z 192.0.2.1 POST /login",
+			"not a log line",
+			// A minute after its last request, the first client starts over
+			"2024-02-01T08:01:50Z 192.0.2.1 GET /",
+		];
+		
+		let path = std::env::temp_dir().join(format!("rustrate-simulator-{}.log", std::process::id()));
+		std::fs::write(&path, log.join("\n")).unwrap();
+		let report = Simulator::replay(path.to_str().unwrap(), 3).await;
+		std::fs::remove_file(&path).unwrap();
+		let report = report.unwrap();
+		
+		assert_eq!((report.requests, report.clients.len(), report.skipped_lines), (6, 2, 1));
+		assert_eq!(report.policies.keys().collect::<Vec<_>>(), ["per-client limit (3/min)"]);
+		
+		let outcome = &report.policies["per-client limit (3/min)"];
+		assert_eq!(outcome.requests, 2);
+		assert_eq!(outcome.clients.get(&"192.0.2.1".parse().unwrap()), Some(&2));
+		assert_eq!(report.blocked_routes.get("GET /search"), Some(&1));
+		assert_eq!(report.blocked_routes.get("POST /login"), Some(&1));
+	}
+}
@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::time::{sleep, Instant};
+use crate::{MAX_TARPIT_CONNECTIONS, TARPIT_DRIP_INTERVAL_MS, TARPIT_MAX_DURATION_SECS};
+
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Holds abusive clients on the line by dripping their error response one byte at a
+/// time. At most `MAX_TARPIT_CONNECTIONS` are held at once; beyond that the response
+/// is sent immediately, so the tarpit can't exhaust the server's own resources.
+pub struct Tarpit;
+
+struct TarpitSlot;
+
+impl Tarpit {
+	pub async fn hold<W: AsyncWrite + Unpin>(stream: &mut W, response: &[u8]) {
+		let Some(_slot) = Self::try_acquire() else {
+			if stream.write_all(response).await.is_ok() {
+				let _ = stream.shutdown().await;
+			}
+			
+			return;
+		};
+		
+		let give_up_at = Instant::now() + Duration::from_secs(TARPIT_MAX_DURATION_SECS);
+		for byte in response.chunks(1) {
+			if Instant::now() >= give_up_at || stream.write_all(byte).await.is_err() || stream.flush().await.is_err() {
+				return;
+			}
+			
+			sleep(Duration::from_millis(TARPIT_DRIP_INTERVAL_MS)).await;
+		}
+		
+		let _ = stream.shutdown().await;
+	}
+	
+	pub fn active() -> usize {
+		ACTIVE.load(Ordering::Acquire)
+	}
+	
+	fn try_acquire() -> Option<TarpitSlot> {
+		ACTIVE
+			.fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| (active < MAX_TARPIT_CONNECTIONS).then_some(active + 1))
+			.ok()
+			.map(|_| TarpitSlot)
+	}
+}
+
+impl Drop for TarpitSlot {
+	fn drop(&mut self) {
+		ACTIVE.fetch_sub(1, Ordering::AcqRel);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::time::timeout;
+	
+	const RESPONSE: &[u8] = b"HTTP/1.0 429 Too Many Requests\r\n\r\n";
+	
+	#[tokio::test]
+	async fn answers_at_once_past_the_cap() {
+		let slots: Vec<TarpitSlot> = (0..MAX_TARPIT_CONNECTIONS).map(|_| Tarpit::try_acquire().unwrap()).collect();
+		assert!(Tarpit::try_acquire().is_none());
+		
+		let mut stream: Vec<u8> = Vec::new();
+		timeout(Duration::from_millis(TARPIT_DRIP_INTERVAL_MS / 2), Tarpit::hold(&mut stream, RESPONSE)).await.unwrap();
+		assert_eq!(stream, RESPONSE);
+		
+		drop(slots);
+		assert_eq!(Tarpit::active(), 0);
+		
+		// Below the cap the response is dripped a byte per interval, and the slot freed once the client is gone
+		let mut stream: Vec<u8> = Vec::new();
+		assert!(timeout(Duration::from_millis(TARPIT_DRIP_INTERVAL_MS / 2), Tarpit::hold(&mut stream, RESPONSE)).await.is_err());
+		assert_eq!(stream, &RESPONSE[..1]);
+		assert_eq!(Tarpit::active(), 0);
+	}
+}
@@ -0,0 +1,354 @@
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+use tokio::{net::TcpStream, time::sleep};
+use crate::core::ConnectionInfo;
+use crate::enums::FingerprintPolicy;
+use crate::TLS_FINGERPRINT_POLICIES;
+
+const RECORD_HEADER_SIZE: usize = 5;
+const MAX_RECORD_SIZE: usize = RECORD_HEADER_SIZE + 16_384;
+const PEEK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+const CONTENT_TYPE_HANDSHAKE: u8 = 0x16;
+const HANDSHAKE_CLIENT_HELLO: u8 = 0x01;
+const EXTENSION_SERVER_NAME: u16 = 0x0000;
+const EXTENSION_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXTENSION_ALPN: u16 = 0x0010;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 0x002b;
+
+/// Fields of a ClientHello that make up its fingerprint, GREASE values left out.
+struct ClientHello<'a> {
+	version: u16,
+	cipher_suites: Vec<u16>,
+	extensions: Vec<u16>,
+	signature_algorithms: Vec<u16>,
+	alpn: Option<&'a [u8]>,
+	has_server_name: bool,
+}
+
+/// JA4-style fingerprints of TLS clients.
+///
+/// Rustls does not expose the extensions of a ClientHello, so the first record is peeked
+/// from the socket before the handshake consumes it. A fingerprint looks like
+/// `t13d1516h2_8daaf6152771_e5627efa2ab1`: protocol, TLS version, SNI, cipher and
+/// extension counts and ALPN, then truncated SHA-256 hashes of the sorted cipher suites
+/// and of the sorted extensions followed by the signature algorithms.
+pub struct TlsFingerprint;
+
+impl TlsFingerprint {
+	/// Fingerprints the ClientHello waiting on `stream` without consuming it. `None` when
+	/// the client does not open with a well-formed ClientHello in a single record.
+	pub async fn peek(stream: &TcpStream) -> Option<String> {
+		let mut buffer = vec![0u8; MAX_RECORD_SIZE];
+		loop {
+			let n = stream.peek(&mut buffer).await.ok()?;
+			if n == 0 || buffer[0] != CONTENT_TYPE_HANDSHAKE {
+				return None;
+			}
+			
+			if n >= RECORD_HEADER_SIZE {
+				let record_size = RECORD_HEADER_SIZE + u16::from_be_bytes([buffer[3], buffer[4]]) as usize;
+				if n >= record_size.min(MAX_RECORD_SIZE) {
+					return parse_client_hello(&buffer[RECORD_HEADER_SIZE..n]).map(|hello| fingerprint(&hello));
+				}
+			}
+			
+			// Peeking returns right away while the rest of the record is in flight
+			sleep(PEEK_RETRY_INTERVAL).await;
+		}
+	}
+	
+	pub fn is_denied(info: &ConnectionInfo) -> bool {
+		let Some(fingerprint) = info.tls_fingerprint.as_deref() else {
+			return false;
+		};
+		
+		TLS_FINGERPRINT_POLICIES.iter().any(|policy| matches!(policy, FingerprintPolicy::Deny(denied) if *denied == fingerprint))
+	}
+	
+	/// Rate limit keys and budgets of the policies matching the fingerprint of `info`.
+	pub fn rate_limits(info: &ConnectionInfo) -> Vec<(String, u8)> {
+		let Some(fingerprint) = info.tls_fingerprint.as_deref() else {
+			return Vec::new();
+		};
+		
+		TLS_FINGERPRINT_POLICIES
+			.iter()
+			.filter_map(|policy| match policy {
+				FingerprintPolicy::RateLimit(limited, limit) if *limited == fingerprint => {
+					Some((format!("ja4:{}", fingerprint), *limit))
+				},
+				_ => None,
+			})
+			.collect()
+	}
+}
+
+struct Reader<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+		if self.data.len() < n {
+			return None;
+		}
+		
+		let (head, tail) = self.data.split_at(n);
+		self.data = tail;
+		Some(head)
+	}
+	
+	fn u8(&mut self) -> Option<u8> {
+		self.take(1).map(|bytes| bytes[0])
+	}
+	
+	fn u16(&mut self) -> Option<u16> {
+		self.take(2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+	}
+	
+	fn u24(&mut self) -> Option<usize> {
+		self.take(3).map(|bytes| u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]) as usize)
+	}
+	
+	fn vec_u8(&mut self) -> Option<Reader<'a>> {
+		let length = self.u8()? as usize;
+		self.take(length).map(|data| Reader { data })
+	}
+	
+	fn vec_u16(&mut self) -> Option<Reader<'a>> {
+		let length = self.u16()? as usize;
+		self.take(length).map(|data| Reader { data })
+	}
+	
+	fn u16_list(mut self) -> Vec<u16> {
+		let mut values: Vec<u16> = Vec::new();
+		while let Some(value) = self.u16() {
+			if !is_grease(value) {
+				values.push(value);
+			}
+		}
+		
+		values
+	}
+}
+
+/*
+ * Handshake layout, after the record header:
+ * type(1) length(3) legacy_version(2) random(32) session_id<1> cipher_suites<2>
+ * compression_methods<1> extensions<2>, each extension being type(2) data<2>
+ */
+fn parse_client_hello(record: &[u8]) -> Option<ClientHello<'_>> {
+	let mut reader = Reader { data: record };
+	if reader.u8()? != HANDSHAKE_CLIENT_HELLO {
+		return None;
+	}
+	
+	let length = reader.u24()?;
+	let mut reader = Reader { data: reader.take(length)? };
+	let mut hello = ClientHello {
+		version: reader.u16()?,
+		cipher_suites: Vec::new(),
+		extensions: Vec::new(),
+		signature_algorithms: Vec::new(),
+		alpn: None,
+		has_server_name: false,
+	};
+	
+	reader.take(32)?;
+	reader.vec_u8()?;
+	hello.cipher_suites = reader.vec_u16()?.u16_list();
+	reader.vec_u8()?;
+	
+	// Extensions are optional before TLS 1.3, but once started they have to be whole
+	if reader.data.is_empty() {
+		return Some(hello);
+	}
+	
+	let mut extensions = reader.vec_u16()?;
+	
+	while let Some(extension) = extensions.u16() {
+		let mut data = extensions.vec_u16()?;
+		if is_grease(extension) {
+			continue;
+		}
+		
+		hello.extensions.push(extension);
+		match extension {
+			EXTENSION_SERVER_NAME => hello.has_server_name = true,
+			EXTENSION_SIGNATURE_ALGORITHMS => hello.signature_algorithms = data.vec_u16()?.u16_list(),
+			EXTENSION_ALPN => hello.alpn = data.vec_u16()?.vec_u8().map(|protocol| protocol.data),
+			EXTENSION_SUPPORTED_VERSIONS => {
+				if let Some(version) = data.vec_u8()?.u16_list().into_iter().max() {
+					hello.version = version;
+				}
+			},
+			_ => (),
+		}
+	}
+	
+	Some(hello)
+}
+
+fn fingerprint(hello: &ClientHello) -> String {
+	let version = match hello.version {
+		0x0304 => "13",
+		0x0303 => "12",
+		0x0302 => "11",
+		0x0301 => "10",
+		0x0300 => "s3",
+		_ => "00",
+	};
+	
+	let mut cipher_suites = hello.cipher_suites.clone();
+	cipher_suites.sort_unstable();
+	
+	// SNI and ALPN already show up in the prefix, so they are left out of the hash
+	let mut extensions: Vec<u16> = hello.extensions
+		.iter()
+		.copied()
+		.filter(|extension| *extension != EXTENSION_SERVER_NAME && *extension != EXTENSION_ALPN)
+		.collect();
+	extensions.sort_unstable();
+	
+	let mut extensions_hash = join_hex(&extensions);
+	if !hello.signature_algorithms.is_empty() {
+		extensions_hash.push('_');
+		extensions_hash.push_str(&join_hex(&hello.signature_algorithms));
+	}
+	
+	format!(
+		"t{}{}{:02}{:02}{}_{}_{}",
+		version,
+		if hello.has_server_name { 'd' } else { 'i' },
+		hello.cipher_suites.len().min(99),
+		hello.extensions.len().min(99),
+		alpn_code(hello.alpn),
+		truncated_hash(&join_hex(&cipher_suites), cipher_suites.is_empty()),
+		truncated_hash(&extensions_hash, extensions.is_empty())
+	)
+}
+
+/// First and last characters of the first ALPN protocol, hex digits when they are not
+/// alphanumeric and `00` without ALPN.
+fn alpn_code(alpn: Option<&[u8]>) -> String {
+	match alpn {
+		Some([first, .., last]) if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() => format!("{}{}", *first as char, *last as char),
+		Some([first, .., last]) => format!("{:x}{:x}", first >> 4, last & 0x0f),
+		Some([only]) if only.is_ascii_alphanumeric() => format!("{}{}", *only as char, *only as char),
+		Some([only]) => format!("{:x}{:x}", only >> 4, only & 0x0f),
+		_ => String::from("00"),
+	}
+}
+
+fn truncated_hash(input: &str, empty: bool) -> String {
+	if empty {
+		return String::from("000000000000");
+	}
+	
+	Sha256::digest(input.as_bytes()).iter().take(6).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn join_hex(values: &[u16]) -> String {
+	values.iter().map(|value| format!("{:04x}", value)).collect::<Vec<String>>().join(",")
+}
+
+/// GREASE values (RFC 8701) are random placeholders like `0x1a1a` that would make every
+/// hello look unique.
+fn is_grease(value: u16) -> bool {
+	value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn vec_u16(data: &[u8]) -> Vec<u8> {
+		[&(data.len() as u16).to_be_bytes()[..], data].concat()
+	}
+	
+	fn extension(extension: u16, data: &[u8]) -> Vec<u8> {
+		[&extension.to_be_bytes()[..], &vec_u16(data)].concat()
+	}
+	
+	fn handshake(body: &[u8]) -> Vec<u8> {
+		[&[HANDSHAKE_CLIENT_HELLO][..], &(body.len() as u32).to_be_bytes()[1..], body].concat()
+	}
+	
+	fn hello_body(extensions: Option<&[u8]>) -> Vec<u8> {
+		let mut body: Vec<u8> = vec![0x03, 0x03];
+		body.extend([0u8; 32]);
+		body.push(0);
+		body.extend(vec_u16(&[0x13, 0x01, 0x0a, 0x0a, 0x13, 0x02]));
+		body.extend([1, 0]);
+		if let Some(extensions) = extensions {
+			body.extend(vec_u16(extensions));
+		}
+		
+		body
+	}
+	
+	fn extensions() -> Vec<u8> {
+		[
+			extension(0x0a0a, &[]),
+			extension(EXTENSION_SERVER_NAME, &[0, 0]),
+			extension(EXTENSION_ALPN, &vec_u16(&[2, b'h', b'2'])),
+			extension(EXTENSION_SUPPORTED_VERSIONS, &[4, 0x03, 0x04, 0x03, 0x03]),
+			extension(EXTENSION_SIGNATURE_ALGORITHMS, &vec_u16(&[0x04, 0x03, 0x08, 0x04])),
+		].concat()
+	}
+	
+	#[test]
+	fn parses_client_hellos() {
+		let record = handshake(&hello_body(Some(&extensions())));
+		let hello = parse_client_hello(&record).unwrap();
+		
+		assert_eq!(hello.version, 0x0304);
+		assert_eq!(hello.cipher_suites, [0x1301, 0x1302]);
+		assert_eq!(hello.extensions, [EXTENSION_SERVER_NAME, EXTENSION_ALPN, EXTENSION_SUPPORTED_VERSIONS, EXTENSION_SIGNATURE_ALGORITHMS]);
+		assert_eq!(hello.signature_algorithms, [0x0403, 0x0804]);
+		assert_eq!(hello.alpn, Some(&b"h2"[..]));
+		assert!(hello.has_server_name);
+		assert!(fingerprint(&hello).starts_with("t13d0204h2_"));
+	}
+	
+	#[test]
+	fn parses_client_hellos_without_extensions() {
+		let record = handshake(&hello_body(None));
+		let hello = parse_client_hello(&record).unwrap();
+		
+		assert_eq!(hello.version, 0x0303);
+		assert!(hello.extensions.is_empty());
+		assert!(fingerprint(&hello).starts_with("t12i0200"));
+	}
+	
+	#[test]
+	fn rejects_truncated_records() {
+		let record = handshake(&hello_body(Some(&extensions())));
+		for length in 0..record.len() {
+			assert!(parse_client_hello(&record[..length]).is_none(), "parsed {} of {} bytes", length, record.len());
+		}
+	}
+	
+	#[test]
+	fn rejects_truncated_fields() {
+		// The handshake length holds, but the fields inside overrun it
+		let body = hello_body(Some(&extensions()));
+		for length in 0..body.len() {
+			if length == hello_body(None).len() {
+				continue;
+			}
+			
+			assert!(parse_client_hello(&handshake(&body[..length])).is_none(), "parsed {} of {} bytes", length, body.len());
+		}
+	}
+	
+	#[test]
+	fn skips_grease_values() {
+		assert!(is_grease(0x0a0a));
+		assert!(is_grease(0xfafa));
+		assert!(!is_grease(0x0a1a));
+		assert!(!is_grease(0x1301));
+	}
+}
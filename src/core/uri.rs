@@ -0,0 +1,212 @@
+use std::borrow::Cow;
+use std::net::Ipv6Addr;
+use bytes::Bytes;
+use crate::core::PathNormalizer;
+use crate::enums::{HttpError, TargetForm};
+
+/// Target of a request with its parts split apart, all of them slices of the request
+/// buffer unless the path had to be normalized.
+///
+/// The authority is the one the request is for: the target's own in absolute and
+/// authority form, where a `Host` header is ignored as RFC 9112 section 3.2.2 asks,
+/// otherwise the `Host` header. Routing should use it rather than reading `Host`.
+#[derive(Debug, Clone)]
+pub struct Uri {
+	form: TargetForm,
+	scheme: Option<&'static str>,
+	authority: Option<Bytes>,
+	path: Bytes,
+	query: Option<Bytes>,
+}
+
+impl Uri {
+	/// Parses a target already checked to be visible ASCII, `host` being the value of the
+	/// request's single `Host` header.
+	pub fn parse(target: &Bytes, host: Option<&Bytes>) -> Result<Self, HttpError> {
+		let raw = std::str::from_utf8(target).map_err(|_| HttpError::InvalidRequestTarget)?;
+		let form = TargetForm::of(raw);
+		
+		match form {
+			TargetForm::Origin => {
+				let (path, query) = split_query(raw);
+				Ok(Self { form, scheme: None, authority: host_authority(host)?, path: normalize(target, path)?, query: query.map(|query| target.slice_ref(query.as_bytes())) })
+			},
+			TargetForm::Absolute => {
+				let (scheme, rest) = raw.split_once("://").unwrap_or_default();
+				let scheme = ["http", "https"].into_iter().find(|known| known.eq_ignore_ascii_case(scheme)).ok_or(HttpError::InvalidRequestTarget)?;
+				
+				let (authority, path_and_query) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+				if !is_valid_authority(authority, false) {
+					return Err(HttpError::InvalidRequestTarget);
+				}
+				
+				let (path, query) = split_query(path_and_query);
+				let path = if path.is_empty() { Bytes::from_static(b"/") } else { normalize(target, path)? };
+				
+				Ok(Self { form, scheme: Some(scheme), authority: Some(target.slice_ref(authority.as_bytes())), path, query: query.map(|query| target.slice_ref(query.as_bytes())) })
+			},
+			TargetForm::Authority => {
+				if !is_valid_authority(raw, true) {
+					return Err(HttpError::InvalidRequestTarget);
+				}
+				
+				Ok(Self { form, scheme: None, authority: Some(target.clone()), path: Bytes::new(), query: None })
+			},
+			TargetForm::Asterisk => Ok(Self { form, scheme: None, authority: host_authority(host)?, path: target.clone(), query: None }),
+		}
+	}
+	
+	pub fn form(&self) -> TargetForm {
+		self.form
+	}
+	
+	/// Scheme of an absolute-form target, lowercased.
+	pub fn scheme(&self) -> Option<&str> {
+		self.scheme
+	}
+	
+	/// `host[:port]` the request is for, `None` when an HTTP/1.0 client sent no `Host`.
+	pub fn authority(&self) -> Option<&str> {
+		self.authority.as_deref().map(as_str)
+	}
+	
+	/// Host of the authority, IPv6 addresses keeping their brackets.
+	pub fn host(&self) -> Option<&str> {
+		self.authority().map(|authority| split_host_port(authority).0)
+	}
+	
+	pub fn port(&self) -> Option<u16> {
+		self.authority().and_then(|authority| split_host_port(authority).1).and_then(|port| port.parse().ok())
+	}
+	
+	/// Normalized path, `*` in asterisk form and empty in authority form.
+	pub fn path(&self) -> &str {
+		as_str(&self.path)
+	}
+	
+	/// Raw query string, without the `?`.
+	pub fn query(&self) -> Option<&str> {
+		self.query.as_deref().map(as_str)
+	}
+}
+
+fn as_str(bytes: &[u8]) -> &str {
+	// Only visible ASCII and normalized paths get in
+	std::str::from_utf8(bytes).unwrap_or_default()
+}
+
+fn split_query(target: &str) -> (&str, Option<&str>) {
+	match target.split_once('?') {
+		Some((path, query)) => (path, Some(query)),
+		None => (target, None),
+	}
+}
+
+fn normalize(target: &Bytes, path: &str) -> Result<Bytes, HttpError> {
+	Ok(match PathNormalizer::normalize(path)? {
+		Cow::Borrowed(path) => target.slice_ref(path.as_bytes()),
+		Cow::Owned(path) => Bytes::from(path),
+	})
+}
+
+/// An empty `Host` is what clients send for targets without authority.
+fn host_authority(host: Option<&Bytes>) -> Result<Option<Bytes>, HttpError> {
+	match host {
+		Some(host) if host.is_empty() => Ok(None),
+		Some(host) if is_valid_authority(as_str(host), false) => Ok(Some(host.clone())),
+		Some(_) => Err(HttpError::InvalidHost),
+		None => Ok(None),
+	}
+}
+
+/*
+ * authority = host [ ":" port ], userinfo being deprecated for http(s)
+ * host      = "[" IPv6address "]" / IPv4address / reg-name
+ */
+fn is_valid_authority(authority: &str, port_required: bool) -> bool {
+	let (host, port) = split_host_port(authority);
+	let valid_host = match host.strip_prefix('[').and_then(|host| host.strip_suffix(']')) {
+		Some(ip) => ip.parse::<Ipv6Addr>().is_ok(),
+		None => !host.is_empty() && host.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=%".contains(&byte)),
+	};
+	
+	let valid_port = match port {
+		Some(port) => port.bytes().all(|byte| byte.is_ascii_digit()) && (port.is_empty() || port.parse::<u16>().is_ok()) && !(port_required && port.is_empty()),
+		None => !port_required,
+	};
+	
+	valid_host && valid_port
+}
+
+fn split_host_port(authority: &str) -> (&str, Option<&str>) {
+	if authority.starts_with('[') {
+		return match authority.find(']').map(|end| authority.split_at(end + 1)) {
+			Some((host, "")) => (host, None),
+			Some((host, rest)) if rest.starts_with(':') => (host, Some(&rest[1..])),
+			_ => (authority, None),
+		};
+	}
+	
+	match authority.rsplit_once(':') {
+		Some((host, port)) => (host, Some(port)),
+		None => (authority, None),
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn parse(target: &'static str, host: Option<&'static str>) -> Result<Uri, HttpError> {
+		Uri::parse(&Bytes::from_static(target.as_bytes()), host.map(|host| Bytes::from_static(host.as_bytes())).as_ref())
+	}
+	
+	#[test]
+	fn parses_origin_form() {
+		let uri = parse("/a/./b?x=1", Some("[::1]:8443")).unwrap();
+		assert_eq!((uri.form(), uri.scheme(), uri.path(), uri.query()), (TargetForm::Origin, None, "/a/b", Some("x=1")));
+		assert_eq!((uri.authority(), uri.host(), uri.port()), (Some("[::1]:8443"), Some("[::1]"), Some(8443)));
+		
+		assert_eq!(parse("/a", None).unwrap().authority(), None);
+		assert_eq!(parse("/a", Some("")).unwrap().authority(), None);
+	}
+	
+	#[test]
+	fn parses_absolute_form() {
+		let uri = parse("http://Example.com:8080/a/../b?x=1", Some("ignored.example")).unwrap();
+		assert_eq!(uri.form(), TargetForm::Absolute);
+		assert_eq!((uri.scheme(), uri.authority(), uri.host(), uri.port()), (Some("http"), Some("Example.com:8080"), Some("Example.com"), Some(8080)));
+		assert_eq!((uri.path(), uri.query()), ("/b", Some("x=1")));
+		
+		let uri = parse("HTTPS://example.com?x", None).unwrap();
+		assert_eq!((uri.scheme(), uri.path(), uri.query()), (Some("https"), "/", Some("x")));
+	}
+	
+	#[test]
+	fn parses_authority_and_asterisk_forms() {
+		let uri = parse("example.com:443", None).unwrap();
+		assert_eq!((uri.form(), uri.authority(), uri.port(), uri.path()), (TargetForm::Authority, Some("example.com:443"), Some(443), ""));
+		
+		let uri = parse("*", Some("example.com")).unwrap();
+		assert_eq!((uri.form(), uri.authority(), uri.path()), (TargetForm::Asterisk, Some("example.com"), "*"));
+	}
+	
+	#[test]
+	fn rejects_invalid_targets_and_hosts() {
+		let rejected = [
+			("example.com", None, HttpError::InvalidRequestTarget),
+			("ftp://example.com/", None, HttpError::InvalidRequestTarget),
+			("http://user@example.com/", None, HttpError::InvalidRequestTarget),
+			("http:///a", None, HttpError::InvalidRequestTarget),
+			("http://[::1/", None, HttpError::InvalidRequestTarget),
+			("/", Some("exa mple.com"), HttpError::InvalidHost),
+			("/", Some("user@example.com"), HttpError::InvalidHost),
+			("/", Some("example.com:http"), HttpError::InvalidHost),
+			("*", Some("example.com:99999"), HttpError::InvalidHost),
+		];
+		for (target, host, error) in rejected {
+			assert_eq!(parse(target, host).err(), Some(error), "{} with Host {:?}", target, host);
+		}
+	}
+}
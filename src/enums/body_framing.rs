@@ -0,0 +1,10 @@
+/// How the end of a request body is found (RFC 9112 section 6.3).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyFraming {
+	/// Neither `Content-Length` nor `Transfer-Encoding`, the request has no body.
+	None,
+	/// `Content-Length` bytes.
+	Length(usize),
+	/// `Transfer-Encoding: chunked`, up to the last chunk and its trailer section.
+	Chunked,
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionOverflow {
+	/// Accept and immediately close connections above the limit.
+	Close,
+	/// Stop accepting while the global limit is reached, leaving new connections in
+	/// the kernel backlog. Connections above the per-client limit are still closed.
+	Backlog,
+}
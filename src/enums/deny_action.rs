@@ -0,0 +1,9 @@
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DenyAction {
+	/// Close the connection right after accepting it.
+	Close,
+	/// Answer plain HTTP connections with 403 before closing them. TLS connections
+	/// are still closed, so denied clients never cost a handshake.
+	Forbidden,
+}
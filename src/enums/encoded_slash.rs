@@ -0,0 +1,10 @@
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodedSlash {
+	/// Answer 400 to paths holding `%2F`.
+	Reject,
+	/// Decode `%2F` into a separator before dot-segments are removed.
+	Decode,
+	/// Leave `%2F` encoded inside its segment, so it is never treated as a path separator.
+	Keep,
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FingerprintPolicy {
+	/// Drop TLS connections whose ClientHello has this JA4-style fingerprint.
+	Deny(&'static str),
+	/// Share a requests-per-minute budget between every client with this fingerprint.
+	RateLimit(&'static str, u8),
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoPolicy {
+	/// Drop connections from an ISO 3166-1 country code.
+	DenyCountry(&'static str),
+	/// Drop connections from an autonomous system number.
+	DenyAsn(u32),
+	/// Share a requests-per-minute budget between every client of a country.
+	RateLimitCountry(&'static str, u8),
+	/// Share a requests-per-minute budget between every client of an autonomous system.
+	RateLimitAsn(u32, u8),
+}
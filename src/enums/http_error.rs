@@ -1,6 +1,8 @@
 use std::fmt::{Debug, Display, Formatter};
 use std::error::Error;
+use crate::enums::HttpStatusCode;
 
+#[derive(PartialEq)]
 pub enum HttpError {
 	DecodeUrlFailed,
 	UnsupportedMethod,
@@ -11,11 +13,33 @@ pub enum HttpError {
 	ConnectionClosed,
 	HeadersTooLarge,
 	BodyTooLarge,
-	BodyNotFound,
 	RequestLineNotFound,
 	RequestTimeout,
 	NotImplemented,
 	TooManyRequests,
+	ServiceUnavailable,
+	SlowClient,
+	InvalidRequestLine,
+	InvalidMethod,
+	InvalidRequestTarget,
+	UriTooLong,
+	InvalidVersion,
+	InvalidLineEnding,
+	HeaderWithoutColon,
+	WhitespaceBeforeColon,
+	InvalidHeaderName,
+	InvalidHeaderValue,
+	ObsoleteLineFolding,
+	InvalidContentLength,
+	IncompleteHead,
+	EncodedSlash,
+	InvalidPath,
+	MissingHost,
+	InvalidHost,
+	ConflictingFraming,
+	InvalidTransferEncoding,
+	InvalidChunk,
+	ExpectationFailed,
 }
 
 impl Debug for HttpError {
@@ -30,11 +54,33 @@ impl Debug for HttpError {
 			HttpError::ConnectionClosed => write!(f, "ConnectionClosed"),
 			HttpError::HeadersTooLarge => write!(f, "HeadersTooLarge"),
 			HttpError::BodyTooLarge => write!(f, "BodyTooLarge"),
-			HttpError::BodyNotFound => write!(f, "BodyNotFound"),
 			HttpError::RequestLineNotFound => write!(f, "RequestLineNotFound"),
 			HttpError::RequestTimeout => write!(f, "RequestTimeout"),
 			HttpError::NotImplemented => write!(f, "NotImplemented"),
 			HttpError::TooManyRequests => write!(f, "TooManyRequests"),
+			HttpError::ServiceUnavailable => write!(f, "ServiceUnavailable"),
+			HttpError::SlowClient => write!(f, "SlowClient"),
+			HttpError::InvalidRequestLine => write!(f, "InvalidRequestLine"),
+			HttpError::InvalidMethod => write!(f, "InvalidMethod"),
+			HttpError::InvalidRequestTarget => write!(f, "InvalidRequestTarget"),
+			HttpError::UriTooLong => write!(f, "UriTooLong"),
+			HttpError::InvalidVersion => write!(f, "InvalidVersion"),
+			HttpError::InvalidLineEnding => write!(f, "InvalidLineEnding"),
+			HttpError::HeaderWithoutColon => write!(f, "HeaderWithoutColon"),
+			HttpError::WhitespaceBeforeColon => write!(f, "WhitespaceBeforeColon"),
+			HttpError::InvalidHeaderName => write!(f, "InvalidHeaderName"),
+			HttpError::InvalidHeaderValue => write!(f, "InvalidHeaderValue"),
+			HttpError::ObsoleteLineFolding => write!(f, "ObsoleteLineFolding"),
+			HttpError::InvalidContentLength => write!(f, "InvalidContentLength"),
+			HttpError::IncompleteHead => write!(f, "IncompleteHead"),
+			HttpError::EncodedSlash => write!(f, "EncodedSlash"),
+			HttpError::InvalidPath => write!(f, "InvalidPath"),
+			HttpError::MissingHost => write!(f, "MissingHost"),
+			HttpError::InvalidHost => write!(f, "InvalidHost"),
+			HttpError::ConflictingFraming => write!(f, "ConflictingFraming"),
+			HttpError::InvalidTransferEncoding => write!(f, "InvalidTransferEncoding"),
+			HttpError::InvalidChunk => write!(f, "InvalidChunk"),
+			HttpError::ExpectationFailed => write!(f, "ExpectationFailed"),
 		}
 	}
 }
@@ -51,11 +97,51 @@ impl Display for HttpError {
 			HttpError::ConnectionClosed => write!(f, "ConnectionClosed"),
 			HttpError::HeadersTooLarge => write!(f, "HeadersTooLarge"),
 			HttpError::BodyTooLarge => write!(f, "BodyTooLarge"),
-			HttpError::BodyNotFound => write!(f, "BodyNotFound"),
 			HttpError::RequestLineNotFound => write!(f, "RequestLineNotFound"),
 			HttpError::RequestTimeout => write!(f, "RequestTimeout"),
 			HttpError::NotImplemented => write!(f, "NotImplemented"),
 			HttpError::TooManyRequests => write!(f, "TooManyRequests"),
+			HttpError::ServiceUnavailable => write!(f, "ServiceUnavailable"),
+			HttpError::SlowClient => write!(f, "SlowClient"),
+			HttpError::InvalidRequestLine => write!(f, "InvalidRequestLine"),
+			HttpError::InvalidMethod => write!(f, "InvalidMethod"),
+			HttpError::InvalidRequestTarget => write!(f, "InvalidRequestTarget"),
+			HttpError::UriTooLong => write!(f, "UriTooLong"),
+			HttpError::InvalidVersion => write!(f, "InvalidVersion"),
+			HttpError::InvalidLineEnding => write!(f, "InvalidLineEnding"),
+			HttpError::HeaderWithoutColon => write!(f, "HeaderWithoutColon"),
+			HttpError::WhitespaceBeforeColon => write!(f, "WhitespaceBeforeColon"),
+			HttpError::InvalidHeaderName => write!(f, "InvalidHeaderName"),
+			HttpError::InvalidHeaderValue => write!(f, "InvalidHeaderValue"),
+			HttpError::ObsoleteLineFolding => write!(f, "ObsoleteLineFolding"),
+			HttpError::InvalidContentLength => write!(f, "InvalidContentLength"),
+			HttpError::IncompleteHead => write!(f, "IncompleteHead"),
+			HttpError::EncodedSlash => write!(f, "EncodedSlash"),
+			HttpError::InvalidPath => write!(f, "InvalidPath"),
+			HttpError::MissingHost => write!(f, "MissingHost"),
+			HttpError::InvalidHost => write!(f, "InvalidHost"),
+			HttpError::ConflictingFraming => write!(f, "ConflictingFraming"),
+			HttpError::InvalidTransferEncoding => write!(f, "InvalidTransferEncoding"),
+			HttpError::InvalidChunk => write!(f, "InvalidChunk"),
+			HttpError::ExpectationFailed => write!(f, "ExpectationFailed"),
+		}
+	}
+}
+
+impl HttpError {
+	/// Status answered to a client whose request failed with this error.
+	pub fn status_code(&self) -> HttpStatusCode {
+		match self {
+			HttpError::UriTooLong => HttpStatusCode::UriTooLong,
+			HttpError::HeadersTooLarge => HttpStatusCode::RequestHeaderFieldsTooLarge,
+			HttpError::BodyTooLarge => HttpStatusCode::PayloadTooLarge,
+			HttpError::ExpectationFailed => HttpStatusCode::ExpectationFailed,
+			HttpError::UnsupportedVersion => HttpStatusCode::HttpVersionNotSupported,
+			HttpError::UnsupportedMethod | HttpError::NotImplemented => HttpStatusCode::NotImplemented,
+			HttpError::RequestTimeout | HttpError::SlowClient => HttpStatusCode::Timeout,
+			HttpError::TooManyRequests => HttpStatusCode::TooManyRequests,
+			HttpError::ServiceUnavailable => HttpStatusCode::ServiceUnavailable,
+			_ => HttpStatusCode::BadRequest,
 		}
 	}
 }
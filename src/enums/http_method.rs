@@ -1,46 +1,64 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HttpMethod {
 	Get,
+	Head,
 	Post,
 	Put,
-	Patch,
 	Delete,
-	Head,
-	Unknown(()),
+	Connect,
+	Options,
+	Trace,
+	Patch,
+	/// Any other token, e.g. WebDAV's `PROPFIND`.
+	Extension(String),
 }
 
-impl PartialEq for HttpMethod {
-	fn eq(&self, other: &Self) -> bool {
-		match (self, other) {
-			(HttpMethod::Get, HttpMethod::Get) => true,
-			(HttpMethod::Post, HttpMethod::Post) => true,
-			(HttpMethod::Put, HttpMethod::Put) => true,
-			(HttpMethod::Patch, HttpMethod::Patch) => true,
-			(HttpMethod::Delete, HttpMethod::Delete) => true,
-			(HttpMethod::Head, HttpMethod::Head) => true,
-			_ => false,
+/// Methods are case-sensitive (RFC 9110 section 9.1), so `get` is an extension method.
+impl From<&str> for HttpMethod {
+	fn from(method: &str) -> Self {
+		match method {
+			"GET" => HttpMethod::Get,
+			"HEAD" => HttpMethod::Head,
+			"POST" => HttpMethod::Post,
+			"PUT" => HttpMethod::Put,
+			"DELETE" => HttpMethod::Delete,
+			"CONNECT" => HttpMethod::Connect,
+			"OPTIONS" => HttpMethod::Options,
+			"TRACE" => HttpMethod::Trace,
+			"PATCH" => HttpMethod::Patch,
+			_ => HttpMethod::Extension(String::from(method)),
 		}
 	}
 }
 
 impl HttpMethod {
-	pub fn from_str(version: &str) -> Self {
-		match version.trim().to_ascii_uppercase().as_str() {
-			"GET" => HttpMethod::Get,
-			"POST" => HttpMethod::Post,
-			"PUT" => HttpMethod::Put,
-			"PATCH" => HttpMethod::Patch,
-			"DELETE" => HttpMethod::Delete,
-			"HEAD" => HttpMethod::Head,
-			_ => HttpMethod::Unknown(()),
+	pub fn as_str(&self) -> &str {
+		match self {
+			HttpMethod::Get => "GET",
+			HttpMethod::Head => "HEAD",
+			HttpMethod::Post => "POST",
+			HttpMethod::Put => "PUT",
+			HttpMethod::Delete => "DELETE",
+			HttpMethod::Connect => "CONNECT",
+			HttpMethod::Options => "OPTIONS",
+			HttpMethod::Trace => "TRACE",
+			HttpMethod::Patch => "PATCH",
+			HttpMethod::Extension(method) => method,
 		}
 	}
 	
-	pub fn is_supported(&self) -> bool {
-		matches!(self, HttpMethod::Get | HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch | HttpMethod::Delete | HttpMethod::Head)
+	/// Value of the `Allow` header, every implemented method.
+	pub fn allowed() -> String {
+		[HttpMethod::Get, HttpMethod::Head, HttpMethod::Post, HttpMethod::Put, HttpMethod::Delete, HttpMethod::Options, HttpMethod::Trace, HttpMethod::Patch]
+			.iter()
+			.map(HttpMethod::as_str)
+			.collect::<Vec<&str>>()
+			.join(", ")
 	}
 	
-	pub fn has_body(&self) -> bool {
-		matches!(self, HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch)
+	/// Whether any route handles the method, the others are answered with 501. This is
+	/// not a proxy, so CONNECT has nothing to tunnel to.
+	pub fn is_implemented(&self) -> bool {
+		!matches!(self, HttpMethod::Connect | HttpMethod::Extension(_))
 	}
 }
\ No newline at end of file
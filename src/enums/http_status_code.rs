@@ -1,4 +1,5 @@
 pub enum HttpStatusCode {
+	Continue,
 	Ok,
 	Created,
 	NoContent,
@@ -7,17 +8,23 @@ pub enum HttpStatusCode {
 	Forbidden,
 	NotFound,
 	Timeout,
+	PayloadTooLarge,
+	UriTooLong,
+	ExpectationFailed,
 	TooManyRequests,
+	RequestHeaderFieldsTooLarge,
 	InternalServerError,
 	NotImplemented,
 	BadGateway,
 	ServiceUnavailable,
+	HttpVersionNotSupported,
 	Unknown(u16),
 }
 
 impl HttpStatusCode {
 	pub fn code(&self) -> u16 {
 		match self {
+			HttpStatusCode::Continue => 100,
 			HttpStatusCode::Ok => 200,
 			HttpStatusCode::Created => 201,
 			HttpStatusCode::NoContent => 204,
@@ -26,17 +33,23 @@ impl HttpStatusCode {
 			HttpStatusCode::Forbidden => 403,
 			HttpStatusCode::NotFound => 404,
 			HttpStatusCode::Timeout => 408,
+			HttpStatusCode::PayloadTooLarge => 413,
+			HttpStatusCode::UriTooLong => 414,
+			HttpStatusCode::ExpectationFailed => 417,
 			HttpStatusCode::TooManyRequests => 429,
+			HttpStatusCode::RequestHeaderFieldsTooLarge => 431,
 			HttpStatusCode::InternalServerError => 500,
 			HttpStatusCode::NotImplemented => 501,
 			HttpStatusCode::BadGateway => 502,
 			HttpStatusCode::ServiceUnavailable => 503,
+			HttpStatusCode::HttpVersionNotSupported => 505,
 			HttpStatusCode::Unknown(code) => *code,
 		}
 	}
 	
 	pub fn reason(&self) -> &'static str {
 		match self {
+			HttpStatusCode::Continue => "Continue",
 			HttpStatusCode::Ok => "OK",
 			HttpStatusCode::Created => "Created",
 			HttpStatusCode::NoContent => "No Content",
@@ -45,11 +58,16 @@ impl HttpStatusCode {
 			HttpStatusCode::Forbidden => "Forbidden",
 			HttpStatusCode::NotFound => "Not Found",
 			HttpStatusCode::Timeout => "Request Timeout",
+			HttpStatusCode::PayloadTooLarge => "Content Too Large",
+			HttpStatusCode::UriTooLong => "URI Too Long",
+			HttpStatusCode::ExpectationFailed => "Expectation Failed",
 			HttpStatusCode::TooManyRequests => "Too Many Requests",
+			HttpStatusCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
 			HttpStatusCode::InternalServerError => "Internal Server Error",
 			HttpStatusCode::NotImplemented => "Not Implemented",
 			HttpStatusCode::BadGateway => "Bad Gateway",
 			HttpStatusCode::ServiceUnavailable => "Service Unavailable",
+			HttpStatusCode::HttpVersionNotSupported => "HTTP Version Not Supported",
 			HttpStatusCode::Unknown(_) => "Unknown",
 		}
 	}
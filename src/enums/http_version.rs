@@ -7,8 +7,8 @@ pub enum HttpVersion {
 	Unknown(()),
 }
 
-impl HttpVersion {
-	pub fn from_str(version: &str) -> Self {
+impl From<&str> for HttpVersion {
+	fn from(version: &str) -> Self {
 		match version {
 			"HTTP/1.0" => HttpVersion::Http10,
 			"HTTP/1.1" => HttpVersion::Http11,
@@ -17,6 +17,18 @@ impl HttpVersion {
 			_ => HttpVersion::Unknown(()),
 		}
 	}
+}
+
+impl HttpVersion {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			HttpVersion::Http10 => "HTTP/1.0",
+			HttpVersion::Http11 => "HTTP/1.1",
+			HttpVersion::Http20 => "HTTP/2",
+			HttpVersion::Http30 => "HTTP/3",
+			HttpVersion::Unknown(_) => "",
+		}
+	}
 	
 	pub fn is_supported(&self) -> bool {
 		matches!(self, HttpVersion::Http10 | HttpVersion::Http11)
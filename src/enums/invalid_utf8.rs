@@ -0,0 +1,8 @@
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidUtf8 {
+	/// Answer 400 to paths whose escapes do not decode to UTF-8.
+	Reject,
+	/// Replace the offending bytes with U+FFFD.
+	Replace,
+}
\ No newline at end of file
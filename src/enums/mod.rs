@@ -2,8 +2,30 @@ mod http_status_code;
 mod http_version;
 mod http_method;
 mod http_error;
+mod route_priority;
+mod connection_overflow;
+mod rate_limit_decision;
+mod deny_action;
+mod geo_policy;
+mod fingerprint_policy;
+mod query_error;
+mod encoded_slash;
+mod invalid_utf8;
+mod target_form;
+mod body_framing;
 
 pub use http_version::HttpVersion;
 pub use http_method::HttpMethod;
 pub use http_error::HttpError;
 pub use http_status_code::HttpStatusCode;
+pub use route_priority::RoutePriority;
+pub use connection_overflow::ConnectionOverflow;
+pub use rate_limit_decision::RateLimitDecision;
+pub use deny_action::DenyAction;
+pub use geo_policy::GeoPolicy;
+pub use fingerprint_policy::FingerprintPolicy;
+pub use query_error::QueryError;
+pub use encoded_slash::EncodedSlash;
+pub use invalid_utf8::InvalidUtf8;
+pub use target_form::TargetForm;
+pub use body_framing::BodyFraming;
@@ -0,0 +1,25 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+
+/// Why a query string could not be turned into the expected parameters.
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+	/// A required parameter is absent.
+	Missing(String),
+	/// A parameter expected once was given several times.
+	Duplicate(String),
+	/// A value does not parse into the expected type.
+	Invalid { name: String, value: String, reason: String },
+}
+
+impl Display for QueryError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			QueryError::Missing(name) => write!(f, "missing query parameter `{}`", name),
+			QueryError::Duplicate(name) => write!(f, "query parameter `{}` is given more than once", name),
+			QueryError::Invalid { name, value, reason } => write!(f, "invalid value {:?} for query parameter `{}`: {}", value, name, reason),
+		}
+	}
+}
+
+impl Error for QueryError {}
\ No newline at end of file
@@ -0,0 +1,10 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitDecision {
+	Allow,
+	/// Answer with a proof-of-work page; solving it lifts the client to the verified tier.
+	Challenge,
+	/// Answer with 429 right away.
+	Reject,
+	/// Answer with 429, dripped byte by byte to slow the client down.
+	Tarpit,
+}
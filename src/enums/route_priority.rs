@@ -0,0 +1,31 @@
+use crate::ROUTE_PRIORITIES;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoutePriority {
+	Critical,
+	High,
+	Normal,
+	Low,
+}
+
+impl RoutePriority {
+	/// Priority of the longest `ROUTE_PRIORITIES` prefix matching `path`, `Normal` otherwise.
+	pub fn from_path(path: &str) -> Self {
+		ROUTE_PRIORITIES
+			.iter()
+			.filter(|(prefix, _)| path.starts_with(prefix))
+			.max_by_key(|(prefix, _)| prefix.len())
+			.map(|(_, priority)| *priority)
+			.unwrap_or(RoutePriority::Normal)
+	}
+	
+	/// Share of the concurrency limit a priority may use; lower priorities are shed first.
+	pub fn limit_share(&self) -> f64 {
+		match self {
+			RoutePriority::Critical => 1.0,
+			RoutePriority::High => 0.9,
+			RoutePriority::Normal => 0.75,
+			RoutePriority::Low => 0.5,
+		}
+	}
+}
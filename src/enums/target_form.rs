@@ -0,0 +1,24 @@
+/// Shapes of a request target (RFC 9112 section 3.2).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetForm {
+	/// `/path?query`, the authority comes from the `Host` header.
+	Origin,
+	/// `http://host/path?query`, as sent to proxies.
+	Absolute,
+	/// `host:port`, only for CONNECT.
+	Authority,
+	/// `*`, only for a server-wide OPTIONS.
+	Asterisk,
+}
+
+impl TargetForm {
+	/// Form of a target, told apart by shape alone.
+	pub fn of(target: &str) -> Self {
+		match target {
+			"*" => TargetForm::Asterisk,
+			_ if target.starts_with('/') => TargetForm::Origin,
+			_ if target.contains("://") => TargetForm::Absolute,
+			_ => TargetForm::Authority,
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+use crate::enums::{ConnectionOverflow, DenyAction, EncodedSlash, FingerprintPolicy, GeoPolicy, InvalidUtf8, RoutePriority};
+
+pub mod core;
+pub mod protocols;
+pub mod traits;
+pub mod utils;
+pub mod enums;
+pub mod listener;
+pub mod stores;
+
+pub const MAX_HEADERS_SIZE: usize = 2048;
+pub const MAX_HEADERS_LENGTH: usize = 25;
+pub const MAX_REQUEST_TARGET_LENGTH: usize = 1024;
+pub const ENCODED_SLASH: EncodedSlash = EncodedSlash::Reject;
+// Collapse `//` into `/` while normalizing paths, the trailing slash is always kept
+pub const MERGE_SLASHES: bool = true;
+pub const INVALID_UTF8_PATH: InvalidUtf8 = InvalidUtf8::Reject;
+pub const MAX_BODY_SIZE: usize = 2_097_152; // 2MB
+// Requests a client may send ahead on one connection before their responses, the next one gets a 503
+pub const MAX_PIPELINE_DEPTH: usize = 16;
+pub const MAX_REQUEST_PER_MINUTE: u8 = 100;
+pub const RATE_LIMIT_WINDOW_SECS: u64 = 60;
+pub const RATE_LIMIT_SNAPSHOT_PATH: &str = "rate_limiter.snapshot";
+pub const RATE_LIMIT_SNAPSHOT_INTERVAL_SECS: u64 = 30;
+pub const INITIAL_CONCURRENCY_LIMIT: usize = 100;
+pub const MIN_CONCURRENCY_LIMIT: usize = 10;
+pub const MAX_CONCURRENCY_LIMIT: usize = 1000;
+pub const TARGET_LATENCY_MS: u64 = 500;
+pub const RETRY_AFTER_SECS: u64 = 1;
+pub const ROUTE_PRIORITIES: &[(&str, RoutePriority)] = &[
+	("/health", RoutePriority::Critical),
+];
+// (path prefix, max in-flight requests, max queued requests)
+pub const ROUTE_BULKHEADS: &[(&str, usize, usize)] = &[
+	("/export", 4, 8),
+];
+pub const BULKHEAD_QUEUE_TIMEOUT_MS: u64 = 5000;
+pub const MAX_CONNECTIONS: usize = 10_000;
+pub const MAX_CONNECTIONS_PER_CLIENT: usize = 64;
+pub const CONNECTION_LIMIT_IPV4_PREFIX: u32 = 32;
+pub const CONNECTION_LIMIT_IPV6_PREFIX: u32 = 64;
+pub const CONNECTION_OVERFLOW: ConnectionOverflow = ConnectionOverflow::Close;
+pub const TLS_HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+pub const MAX_TLS_HANDSHAKES_PER_MINUTE: u8 = 30;
+pub const HEADER_READ_TIMEOUT_SECS: u64 = 6;
+pub const BODY_READ_TIMEOUT_SECS: u64 = 12;
+pub const RESPONSE_WRITE_TIMEOUT_SECS: u64 = 12;
+pub const MIN_DATA_RATE_BYTES_PER_SEC: u64 = 512;
+pub const MIN_DATA_RATE_WINDOW_SECS: u64 = 3;
+pub const SLOW_CLIENT_PENALTY: u8 = 20;
+// Clients this far past MAX_REQUEST_PER_MINUTE get tarpitted instead of rejected, None disables the tarpit
+pub const TARPIT_THRESHOLD: Option<u8> = Some(150);
+pub const MAX_TARPIT_CONNECTIONS: usize = 256;
+pub const TARPIT_DRIP_INTERVAL_MS: u64 = 1000;
+pub const TARPIT_MAX_DURATION_SECS: u64 = 120;
+// Clients at this count are served a proof-of-work challenge before the hard limit, None disables it
+pub const POW_CHALLENGE_THRESHOLD: Option<u8> = Some(60);
+pub const POW_DIFFICULTY_BITS: u8 = 16;
+pub const POW_CHALLENGE_TTL_SECS: u64 = 300;
+// Signed with RUSTRATE_POW_SECRET, which has to be shared by every instance behind the same domain
+pub const POW_PASS_TTL_SECS: u64 = 3600;
+pub const POW_VERIFY_PATH: &str = "/.well-known/rustrate/pow";
+pub const VERIFIED_MAX_REQUEST_PER_MINUTE: u8 = 200;
+// Networks bypassing rate limiting, extended by the file at RUSTRATE_ALLOWLIST_FILE
+pub const ALLOWED_CIDRS: &[&str] = &[];
+// Networks dropped at accept time, extended by the file at RUSTRATE_DENYLIST_FILE
+pub const DENIED_CIDRS: &[&str] = &[];
+pub const DENY_ACTION: DenyAction = DenyAction::Close;
+pub const IP_LIST_RELOAD_INTERVAL_SECS: u64 = 5;
+// Evaluated against the databases at RUSTRATE_GEOIP_COUNTRY_DB / RUSTRATE_GEOIP_ASN_DB
+pub const GEO_POLICIES: &[GeoPolicy] = &[];
+pub const GEOIP_RELOAD_INTERVAL_SECS: u64 = 60;
+// Matched against the JA4-style fingerprint of each ClientHello, see TlsFingerprint
+pub const TLS_FINGERPRINT_POLICIES: &[FingerprintPolicy] = &[];
+pub const ADMIN_TOP_KEYS: usize = 20;
+pub const ADMIN_DEFAULT_BAN_SECS: u64 = 3600;
+pub const ADMIN_MAX_BAN_SECS: u64 = 365 * 24 * 3600;
@@ -0,0 +1,281 @@
+use std::{
+	collections::HashMap,
+	error::Error,
+	io::{Error as IoError, ErrorKind},
+	time::Duration
+};
+use bytes::BytesMut;
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+	time::timeout
+};
+use crate::{
+	core::{HttpRequest, IpFilter, RateLimiter},
+	enums::{HttpError, HttpMethod, HttpStatusCode},
+	protocols::HttpV11,
+	traits::StoreError,
+	utils::helper::json_string,
+	ADMIN_DEFAULT_BAN_SECS,
+	ADMIN_MAX_BAN_SECS,
+	ADMIN_TOP_KEYS,
+	GEO_POLICIES,
+	HEADER_READ_TIMEOUT_SECS,
+	MAX_HEADERS_SIZE,
+	MAX_REQUEST_PER_MINUTE,
+	POW_CHALLENGE_THRESHOLD,
+	RATE_LIMIT_WINDOW_SECS,
+	TARPIT_THRESHOLD,
+	TLS_FINGERPRINT_POLICIES,
+	VERIFIED_MAX_REQUEST_PER_MINUTE
+};
+
+/*
+ * Routes, all answering JSON and requiring `Authorization: Bearer <RUSTRATE_ADMIN_TOKEN>`:
+ * GET    /keys[?limit=N]           busiest counters
+ * GET    /keys/<key>               counter and ban of a key
+ * DELETE /keys/<key>               reset the counter and lift the ban
+ * GET    /bans                     active bans
+ * POST   /bans/<key>[?secs=N]      ban a key, for ADMIN_DEFAULT_BAN_SECS by default and ADMIN_MAX_BAN_SECS at most
+ * DELETE /bans/<key>               lift a ban
+ * GET    /policies                 configured limits and policies, with the live allow and deny lists
+ */
+pub async fn handle<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, token: &str) -> Result<(), Box<dyn Error>> {
+	let mut header_buffer: BytesMut = BytesMut::zeroed(MAX_HEADERS_SIZE);
+	
+	let bytes_read = timeout(Duration::from_secs(HEADER_READ_TIMEOUT_SECS), read_head(&mut stream, &mut header_buffer)).await??;
+	if bytes_read == 0 {
+		return Err(Box::new(HttpError::ConnectionClosed));
+	}
+	
+	header_buffer.truncate(bytes_read);
+	let req = HttpRequest::new(header_buffer.freeze()).await.ok();
+	let (status, body) = match req {
+		Some(req) if !is_authorized(&req, token) => (HttpStatusCode::Unauthorized, error_body("missing or invalid admin token")),
+		Some(req) => route(&req).await,
+		None => (HttpStatusCode::BadRequest, error_body("malformed request")),
+	};
+	
+	let res = HttpV11::from_body(status, "application/json", &body);
+	stream.write_all(res.as_bytes()).await?;
+	stream.shutdown().await?;
+	
+	Ok(())
+}
+
+/// Reads until `buffer` holds a complete head, is full or the client stops sending, as a
+/// head can arrive in several segments.
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S, buffer: &mut [u8]) -> Result<usize, IoError> {
+	let mut bytes_read: usize = 0;
+	while bytes_read < buffer.len() && matches!(HttpRequest::head_length(&buffer[..bytes_read]), Ok(None)) {
+		let n = stream.read(&mut buffer[bytes_read..]).await?;
+		if n == 0 {
+			break;
+		}
+		
+		bytes_read += n;
+	}
+	
+	Ok(bytes_read)
+}
+
+async fn route(req: &HttpRequest) -> (HttpStatusCode, String) {
+	let path = req.path();
+	let segments: Vec<&str> = path.trim_matches('/').splitn(2, '/').collect();
+	
+	match (&req.method, segments.as_slice()) {
+		(HttpMethod::Get, ["keys"]) => {
+			let limit = match req.query().optional("limit") {
+				Ok(limit) => limit.unwrap_or(ADMIN_TOP_KEYS),
+				Err(e) => return (HttpStatusCode::BadRequest, error_body(&e.to_string())),
+			};
+			
+			match (RateLimiter::top(limit).await, RateLimiter::bans().await) {
+				(Ok(entries), Ok(bans)) => {
+					let bans: HashMap<String, Duration> = bans.into_iter().collect();
+					let keys: Vec<String> = entries.iter().map(|(key, count)| key_json(key, Some(*count), bans.get(key).copied())).collect();
+					(HttpStatusCode::Ok, format!("{{\"keys\":[{}]}}", keys.join(",")))
+				},
+				(Err(e), _) | (_, Err(e)) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+			}
+		},
+		(HttpMethod::Get, ["keys", key]) => match key_state(key).await {
+			Ok(body) => (HttpStatusCode::Ok, body),
+			Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+		},
+		(HttpMethod::Delete, ["keys", key]) => match RateLimiter::reset(key).await {
+			Ok(_) => (HttpStatusCode::Ok, key_json(key, None, None)),
+			Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+		},
+		(HttpMethod::Get, ["bans"]) => {
+			match RateLimiter::bans().await {
+				Ok(bans) => {
+					let bans: Vec<String> = bans
+						.iter()
+						.map(|(key, remaining)| format!("{{\"key\":{},\"expires_in_secs\":{}}}", json_string(key), remaining.as_secs()))
+						.collect();
+					(HttpStatusCode::Ok, format!("{{\"bans\":[{}]}}", bans.join(",")))
+				},
+				Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+			}
+		},
+		(HttpMethod::Post, ["bans", key]) => {
+			let secs = match req.query().optional("secs") {
+				Ok(secs) => secs.unwrap_or(ADMIN_DEFAULT_BAN_SECS),
+				Err(e) => return (HttpStatusCode::BadRequest, error_body(&e.to_string())),
+			};
+			
+			if secs > ADMIN_MAX_BAN_SECS {
+				return (HttpStatusCode::BadRequest, error_body(&format!("secs must be at most {}", ADMIN_MAX_BAN_SECS)));
+			}
+			
+			match RateLimiter::ban(key, Duration::from_secs(secs)).await {
+				Ok(_) => match key_state(key).await {
+					Ok(body) => (HttpStatusCode::Created, body),
+					Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+				},
+				Err(e) if e.downcast_ref::<IoError>().is_some_and(|e| e.kind() == ErrorKind::InvalidInput) => (HttpStatusCode::BadRequest, error_body(&e.to_string())),
+				Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+			}
+		},
+		(HttpMethod::Delete, ["bans", key]) => match RateLimiter::unban(key).await {
+			Ok(true) => match RateLimiter::get(key).await {
+				Ok(count) => (HttpStatusCode::Ok, key_json(key, count, None)),
+				Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+			},
+			Ok(false) => (HttpStatusCode::NotFound, error_body("key is not banned")),
+			Err(e) => (HttpStatusCode::BadGateway, error_body(&e.to_string())),
+		},
+		(HttpMethod::Get, ["policies"]) => (HttpStatusCode::Ok, policies_json()),
+		_ => (HttpStatusCode::NotFound, error_body("unknown admin route")),
+	}
+}
+
+fn is_authorized(req: &HttpRequest, token: &str) -> bool {
+	let Some(provided) = req.headers.get("Authorization").and_then(|value| value.strip_prefix("Bearer ")) else {
+		return false;
+	};
+	
+	// Compared in constant time so the token cannot be guessed byte by byte
+	provided.len() == token.len() && provided.bytes().zip(token.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Counter and ban of `key` as read from the store, which may be unreachable.
+async fn key_state(key: &str) -> Result<String, StoreError> {
+	Ok(key_json(key, RateLimiter::get(key).await?, RateLimiter::ban_remaining(key).await?))
+}
+
+fn key_json(key: &str, count: Option<u8>, ban: Option<Duration>) -> String {
+	format!(
+		"{{\"key\":{},\"count\":{},\"banned\":{},\"ban_expires_in_secs\":{}}}",
+		json_string(key),
+		count.unwrap_or(0),
+		ban.is_some(),
+		ban.map_or(String::from("null"), |remaining| remaining.as_secs().to_string())
+	)
+}
+
+fn policies_json() -> String {
+	let strings = |values: Vec<String>| values.iter().map(|value| json_string(value)).collect::<Vec<String>>().join(",");
+	let threshold = |threshold: Option<u8>| threshold.map_or(String::from("null"), |threshold| threshold.to_string());
+	
+	format!(
+		"{{\"window_secs\":{},\"max_requests\":{},\"verified_max_requests\":{},\"challenge_threshold\":{},\"tarpit_threshold\":{},\"allowed_cidrs\":[{}],\"denied_cidrs\":[{}],\"geo_policies\":[{}],\"tls_fingerprint_policies\":[{}]}}",
+		RATE_LIMIT_WINDOW_SECS,
+		MAX_REQUEST_PER_MINUTE,
+		VERIFIED_MAX_REQUEST_PER_MINUTE,
+		threshold(POW_CHALLENGE_THRESHOLD),
+		threshold(TARPIT_THRESHOLD),
+		strings(IpFilter::allowed_cidrs()),
+		strings(IpFilter::denied_cidrs()),
+		strings(GEO_POLICIES.iter().map(|policy| format!("{:?}", policy)).collect()),
+		strings(TLS_FINGERPRINT_POLICIES.iter().map(|policy| format!("{:?}", policy)).collect())
+	)
+}
+
+fn error_body(message: &str) -> String {
+	format!("{{\"error\":{}}}", json_string(message))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::io::duplex;
+	
+	const TOKEN: &str = "admin-secret";
+	
+	async fn request(method: &str, target: &str, token: Option<&str>) -> String {
+		let (mut client, server) = duplex(MAX_HEADERS_SIZE);
+		let authorization = token.map_or(String::new(), |token| format!("Authorization: Bearer {}\r\n", token));
+		client.write_all(format!("{} {} HTTP/1.1\r\nHost: admin\r\n{}\r\n", method, target, authorization).as_bytes()).await.unwrap();
+		handle(server, TOKEN).await.unwrap();
+		
+		let mut response = String::new();
+		client.read_to_string(&mut response).await.unwrap();
+		response
+	}
+	
+	fn status_line(response: &str) -> &str {
+		response.lines().next().unwrap_or_default()
+	}
+	
+	fn body(response: &str) -> &str {
+		response.split_once("\r\n\r\n").map_or("", |(_, body)| body)
+	}
+	
+	#[tokio::test]
+	async fn requires_the_token() {
+		assert_eq!(status_line(&request("GET", "/policies", None).await), "HTTP/1.1 401 Unauthorized");
+		assert_eq!(status_line(&request("GET", "/policies", Some("admin-secreT")).await), "HTTP/1.1 401 Unauthorized");
+		assert_eq!(status_line(&request("GET", "/policies", Some("admin")).await), "HTTP/1.1 401 Unauthorized");
+		assert_eq!(status_line(&request("GET", "/policies", Some(TOKEN)).await), "HTTP/1.1 200 OK");
+	}
+	
+	#[tokio::test]
+	async fn routes_by_method_and_path() {
+		assert_eq!(status_line(&request("GET", "/nowhere", Some(TOKEN)).await), "HTTP/1.1 404 Not Found");
+		assert_eq!(status_line(&request("PUT", "/bans/192.0.2.10", Some(TOKEN)).await), "HTTP/1.1 404 Not Found");
+		assert_eq!(status_line(&request("GET", "/keys?limit=many", Some(TOKEN)).await), "HTTP/1.1 400 Bad Request");
+		assert_eq!(status_line(&request("POST", &format!("/bans/192.0.2.10?secs={}", ADMIN_MAX_BAN_SECS + 1), Some(TOKEN)).await), "HTTP/1.1 400 Bad Request");
+		assert_eq!(status_line(&request("DELETE", "/bans/192.0.2.11", Some(TOKEN)).await), "HTTP/1.1 404 Not Found");
+	}
+	
+	#[tokio::test]
+	async fn bans_and_lifts_bans() {
+		RateLimiter::add("192.0.2.12").await;
+		
+		let key = request("GET", "/keys/192.0.2.12", Some(TOKEN)).await;
+		assert_eq!(body(&key), r#"{"key":"192.0.2.12","count":1,"banned":false,"ban_expires_in_secs":null}"#);
+		
+		let banned = request("POST", "/bans/192.0.2.12?secs=60", Some(TOKEN)).await;
+		assert_eq!(status_line(&banned), "HTTP/1.1 201 Created");
+		assert!(body(&banned).contains(r#""banned":true"#));
+		assert!(body(&request("GET", "/bans", Some(TOKEN)).await).contains(r#""key":"192.0.2.12""#));
+		
+		let lifted = request("DELETE", "/bans/192.0.2.12", Some(TOKEN)).await;
+		assert_eq!(body(&lifted), r#"{"key":"192.0.2.12","count":1,"banned":false,"ban_expires_in_secs":null}"#);
+		
+		let reset = request("DELETE", "/keys/192.0.2.12", Some(TOKEN)).await;
+		assert_eq!(status_line(&reset), "HTTP/1.1 200 OK");
+		assert!(body(&request("GET", "/keys/192.0.2.12", Some(TOKEN)).await).contains(r#""count":0"#));
+	}
+	
+	#[tokio::test]
+	async fn waits_for_a_head_sent_in_pieces() {
+		let (mut client, server) = duplex(MAX_HEADERS_SIZE);
+		let pending = tokio::spawn(async move {
+			client.write_all(b"GET /policies HTTP/1.1\r\nHost: admin\r\nAuthor").await.unwrap();
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			client.write_all(format!("ization: Bearer {}\r\n\r\n", TOKEN).as_bytes()).await.unwrap();
+			client
+		});
+		
+		let handled = handle(server, TOKEN);
+		let (handled, client) = tokio::join!(handled, pending);
+		handled.unwrap();
+		
+		let mut response = String::new();
+		client.unwrap().read_to_string(&mut response).await.unwrap();
+		assert_eq!(status_line(&response), "HTTP/1.1 200 OK");
+		assert!(body(&response).contains(r#""allowed_cidrs":["#));
+	}
+}
@@ -1,126 +1,462 @@
 use std::{
 	error::Error,
-	net::SocketAddr,
+	future::poll_fn,
+	task::Poll,
 	time::{Duration}
 };
+use bytes::{Bytes, BytesMut};
 use tokio::{
-	io::{AsyncReadExt, AsyncWriteExt, BufReader},
+	io::{AsyncReadExt, AsyncWriteExt, BufReader, ReadBuf},
 	net::TcpStream,
-	time::{Instant, timeout, timeout_at}
+	time::{Instant, timeout_at}
 };
 use crate::{
 	core::{HttpRequest, HttpResponse},
-	enums::{HttpError, HttpStatusCode},
-	protocols::HttpV10,
+	enums::{BodyFraming, HttpError, HttpStatusCode, QueryError, RateLimitDecision, RoutePriority},
+	protocols::{HttpV10, HttpV11},
 	MAX_HEADERS_SIZE,
-	MAX_BODY_SIZE
+	MAX_BODY_SIZE,
+	RETRY_AFTER_SECS,
+	HEADER_READ_TIMEOUT_SECS,
+	BODY_READ_TIMEOUT_SECS,
+	SLOW_CLIENT_PENALTY,
+	MAX_PIPELINE_DEPTH,
+	POW_VERIFY_PATH
 };
-use crate::core::RateLimiter;
+use crate::utils::data_rate::{read_buf_with_min_rate, read_exact_with_min_rate, DataRate};
+use crate::core::{Bulkhead, Challenge, ChunkedDecoder, ConnectionInfo, HeaderMap, LoadShedder, Query, RateLimiter, Tarpit};
+use crate::traits::{FromQuery, HttpProtocol};
 
-pub async fn handle(stream: TcpStream, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
-	let ip: String = addr.ip().to_string();
-	RateLimiter::add(ip.clone());
+pub async fn handle(stream: TcpStream, info: ConnectionInfo) -> Result<(), Box<dyn Error>> {
+	let ip: String = info.addr.ip().to_string();
 	
 	let mut reader: BufReader<TcpStream> = BufReader::new(stream);
-	if RateLimiter::is_blocked(&ip) {
-		throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::TooManyRequests).await;
-		return Err(Box::new(HttpError::TooManyRequests));
-	}
-	
-	let mut header_buffer: [u8; MAX_HEADERS_SIZE] = [0u8; MAX_HEADERS_SIZE];
 	
-	let total_deadline: Instant = Instant::now() + Duration::from_secs(12);
-	let chunk_deadline = Duration::from_secs(6);
-	
-	let read_result = timeout_at(
-		total_deadline,
-		timeout(chunk_deadline, reader.read(&mut header_buffer))
-	).await;
-	let bytes_read: usize = match read_result {
-		Ok(Ok(Ok(n))) => n,
-		Ok(Ok(Err(e))) => {
-			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::BadRequest).await;
-			return Err(Box::new(e));
-		},
-		_ => {
-			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::Timeout).await;
-			return Err(Box::new(HttpError::RequestTimeout));
+	// Bytes read past the previous request, the start of the next pipelined one
+	let mut leftover: Bytes = Bytes::new();
+	let mut depth: usize = 0;
+	let mut first: bool = true;
+	loop {
+		// A head trickled in or never finished is the classic slowloris
+		let head = match read_head(&mut reader, leftover, first).await {
+			Ok(head) => Ok(head),
+			Err(err) if is_slow_client(err.as_ref()) => Err(HttpError::SlowClient),
+			Err(err) if matches!(err.downcast_ref::<HttpError>(), Some(HttpError::RequestTimeout)) => Err(HttpError::RequestTimeout),
+			Err(err) => return Err(err),
+		};
+		
+		let (head, idle) = match head {
+			Ok(Some(head)) => head,
+			Ok(None) => return Ok(()),
+			Err(err) => {
+				RateLimiter::penalize(&ip, SLOW_CLIENT_PENALTY).await;
+				return Err(Box::new(err));
+			}
+		};
+		
+		// Requests sent ahead, before the answer to the one before: only a client that waited resets the count
+		depth = if first || idle { 0 } else { depth + 1 };
+		first = false;
+		
+		// The request borrows its target, fields and body from the buffer instead of copying them
+		let mut req: HttpRequest = match HttpRequest::new(head).await {
+			Ok(req) => req,
+			Err(e) => {
+				throw_error_and_shutdown(reader.get_mut(), e.status_code()).await;
+				return Err(Box::new(e));
+			}
+		};
+		
+		// The decision needs the pass cookie, so unlike connection limits it waits for the headers
+		let verified = Challenge::has_pass(info.addr.ip(), &req.headers);
+		let decision = RateLimiter::check(&info, verified).await;
+		
+		// Challenged clients still have to reach the verification, banned and rejected ones do not
+		if req.path() == POW_VERIFY_PATH && matches!(decision, RateLimitDecision::Allow | RateLimitDecision::Challenge) {
+			verify_challenge(reader.get_mut(), &info, req.query_as().ok()).await;
+			return Ok(());
 		}
-	};
-	
-	if bytes_read == 0 {
-		return Err(Box::new(HttpError::ConnectionClosed));
-	}
-	
-	let header_buffer: &[u8] = &header_buffer[..bytes_read];
-	let mut req: HttpRequest = HttpRequest::new(header_buffer).await?;
-	read_body(&mut reader, &mut req, total_deadline, chunk_deadline).await?;
-	
-	let res: HttpResponse = HttpResponse::new(req).await?;
-	res.send(reader.into_inner()).await?;
-	
-	Ok(())
-}
-
-async fn read_body(reader: &mut BufReader<TcpStream>, req: &mut HttpRequest, total_deadline: Instant, chunk_deadline: Duration) -> Result<(), Box<dyn Error>> {
-	if req.method.has_body() {
-		if req.headers.contains_key("Transfer-Encoding") {
+		
+		match decision {
+			RateLimitDecision::Allow => (),
+			RateLimitDecision::Challenge => {
+				let page = HttpV10::from_body_with_headers(HttpStatusCode::TooManyRequests, "text/html; charset=utf-8", &Challenge::page(info.addr.ip()), &HeaderMap::new());
+				write_and_shutdown(reader.get_mut(), page.as_bytes()).await;
+				return Err(Box::new(HttpError::TooManyRequests));
+			},
+			RateLimitDecision::Reject => {
+				throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::TooManyRequests).await;
+				return Err(Box::new(HttpError::TooManyRequests));
+			},
+			RateLimitDecision::Tarpit => {
+				Tarpit::hold(reader.get_mut(), HttpV10::from_status_code(HttpStatusCode::TooManyRequests).as_bytes()).await;
+				return Err(Box::new(HttpError::TooManyRequests));
+			}
+		}
+		
+		// The requests past the limit are left unanswered, the client retries them on a new connection
+		if depth > MAX_PIPELINE_DEPTH {
+			throw_service_unavailable(reader.get_mut()).await;
+			return Err(Box::new(HttpError::ServiceUnavailable));
+		}
+		
+		// Extension methods and CONNECT parse fine, but nothing here can act on them
+		if !req.method.is_implemented() {
 			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::NotImplemented).await;
-			return Err(Box::new(HttpError::NotImplemented));
+			return Err(Box::new(HttpError::UnsupportedMethod));
 		}
 		
-		let content_length = req.content_length();
-		if content_length == 0 {
-			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::BadRequest).await;
-			return Err(Box::new(HttpError::BodyNotFound));
+		// Taken before the body is asked for or read, so a request that cannot be served is refused unread
+		let Some(mut guard) = LoadShedder::acquire(RoutePriority::from_path(req.path())) else {
+			throw_service_unavailable(reader.get_mut()).await;
+			return Err(Box::new(HttpError::ServiceUnavailable));
+		};
+		
+		let Ok(permit) = Bulkhead::acquire(req.path()).await else {
+			throw_service_unavailable(reader.get_mut()).await;
+			return Err(Box::new(HttpError::ServiceUnavailable));
+		};
+		
+		req.geo = info.geo.clone();
+		let slow_client = match read_body(&mut reader, &mut req).await {
+			Ok(_) => false,
+			Err(err) if is_slow_client(err.as_ref()) => true,
+			Err(err) => return Err(err),
+		};
+		
+		if slow_client {
+			RateLimiter::penalize(&ip, SLOW_CLIENT_PENALTY).await;
+			return Err(Box::new(HttpError::SlowClient));
 		}
 		
-		if content_length > MAX_BODY_SIZE {
-			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::BadRequest).await;
-			return Err(Box::new(HttpError::BodyTooLarge));
+		leftover = req.take_rest();
+		
+		// Requests are handled one after the other, so responses go out in the order they were asked for
+		guard.start();
+		let res: HttpResponse = HttpResponse::new(req).await?;
+		drop(permit);
+		drop(guard);
+		
+		let slow_client = match res.send(reader.get_mut()).await {
+			Ok(_) => false,
+			Err(err) if is_slow_client(err.as_ref()) => true,
+			Err(err) => return Err(err),
+		};
+		
+		if slow_client {
+			RateLimiter::penalize(&ip, SLOW_CLIENT_PENALTY).await;
+			return Err(Box::new(HttpError::SlowClient));
 		}
 		
-		let remain_buffer_length = content_length.saturating_sub(req.body.len());
-		if remain_buffer_length > 0 {
-			let mut body_buffer: Vec<u8> = Vec::with_capacity(content_length);
-			body_buffer.extend_from_slice(&req.body);
-			
-			{
-				let mut remaining_chunk: Vec<u8> = vec![0u8; remain_buffer_length];
-				let read_result = timeout_at(
-					total_deadline,
-					timeout(chunk_deadline, reader.read_exact(&mut remaining_chunk))
-				).await;
+		if !res.keep_connection_alive {
+			return Ok(());
+		}
+	}
+}
+
+/*
+ * Reads until `leftover` and what follows hold a complete head, or cannot, and tells whether
+ * the client was idle, with nothing sent ahead. A connection closed or left idle between
+ * requests is not an error, only the first request has to come. Heads are held to the
+ * minimum data rate like bodies, from the first request's accept or the next one's first byte.
+ */
+async fn read_head(reader: &mut BufReader<TcpStream>, leftover: Bytes, first: bool) -> Result<Option<(Bytes, bool)>, Box<dyn Error>> {
+	let mut header_buffer: BytesMut = BytesMut::zeroed(MAX_HEADERS_SIZE.max(leftover.len()));
+	header_buffer[..leftover.len()].copy_from_slice(&leftover);
+	
+	let idle = leftover.is_empty() && reader.buffer().is_empty() && !has_pending(reader.get_ref()).await;
+	
+	let header_deadline: Instant = Instant::now() + Duration::from_secs(HEADER_READ_TIMEOUT_SECS);
+	
+	// A head can arrive in several segments, keep reading until it is complete or cannot be
+	let mut bytes_read: usize = leftover.len();
+	let mut rate = DataRate::new();
+	while bytes_read < MAX_HEADERS_SIZE && matches!(HttpRequest::head_length(&header_buffer[..bytes_read]), Ok(None)) {
+		let read_result = timeout_at(rate.window_end().min(header_deadline), reader.read(&mut header_buffer[bytes_read..])).await;
+		let n: usize = match read_result {
+			Ok(Ok(n)) => n,
+			Ok(Err(e)) => {
+				throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::BadRequest).await;
+				return Err(Box::new(e));
+			},
+			Err(_) if !first && bytes_read == 0 => {
+				if Instant::now() >= header_deadline {
+					return Ok(None);
+				}
+				
+				rate = DataRate::new();
+				continue;
+			},
+			Err(_) => {
+				if Instant::now() >= header_deadline {
+					throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::Timeout).await;
+					return Err(Box::new(HttpError::RequestTimeout));
+				}
 				
-				match read_result {
-					Ok(Ok(Ok(_))) => (),
-					Ok(Ok(Err(e))) => {
-						throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::BadRequest).await;
-						return Err(Box::new(e));
-					},
-					_ => {
-						throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::Timeout).await;
-						return Err(Box::new(HttpError::RequestTimeout));
-					}
+				if !rate.record(0) {
+					throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::Timeout).await;
+					return Err(Box::new(HttpError::SlowClient));
 				}
 				
-				body_buffer.extend_from_slice(&remaining_chunk);
+				continue;
 			}
-			
-			req.set_body(body_buffer);
+		};
+		
+		if n == 0 && bytes_read == 0 {
+			return if first { Err(Box::new(HttpError::ConnectionClosed)) } else { Ok(None) };
+		}
+		
+		if n == 0 {
+			break;
+		}
+		
+		// An idle persistent connection is not a slow one, its next request is timed from its first byte
+		if !first && bytes_read == 0 {
+			rate = DataRate::new();
+		}
+		
+		bytes_read += n;
+		if !rate.record(n) {
+			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::Timeout).await;
+			return Err(Box::new(HttpError::SlowClient));
+		}
+	}
+	
+	header_buffer.truncate(bytes_read);
+	Ok(Some((header_buffer.freeze(), idle)))
+}
+
+/// Whether bytes are already waiting on the socket, without reading them.
+async fn has_pending(stream: &TcpStream) -> bool {
+	let mut byte = [0u8; 1];
+	poll_fn(|cx| Poll::Ready(matches!(stream.poll_peek(cx, &mut ReadBuf::new(&mut byte)), Poll::Ready(Ok(n)) if n > 0))).await
+}
+
+pub async fn forbid(mut stream: TcpStream) {
+	throw_error_and_shutdown(&mut stream, HttpStatusCode::Forbidden).await;
+}
+
+async fn read_body(reader: &mut BufReader<TcpStream>, req: &mut HttpRequest) -> Result<(), Box<dyn Error>> {
+	if req.framing == BodyFraming::None {
+		return Ok(());
+	}
+	
+	// Checked before the client is told to go on, so a refused upload is never sent
+	if let BodyFraming::Length(content_length) = req.framing && content_length > MAX_BODY_SIZE {
+		throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::PayloadTooLarge).await;
+		return Err(Box::new(HttpError::BodyTooLarge));
+	}
+	
+	if req.expects_continue() {
+		if let Err(status_code) = HttpV11::check_expectation(req) {
+			throw_error_and_shutdown(reader.get_mut(), status_code).await;
+			return Err(Box::new(HttpError::ExpectationFailed));
 		}
+		
+		reader.get_mut().write_all(HttpV11::interim(HttpStatusCode::Continue).as_bytes()).await?;
+	}
+	
+	let body_deadline: Instant = Instant::now() + Duration::from_secs(BODY_READ_TIMEOUT_SECS);
+	let result = match req.framing {
+		BodyFraming::Length(content_length) => read_sized_body(reader, req, content_length, body_deadline).await,
+		_ => read_chunked_body(reader, req, body_deadline).await,
+	};
+	
+	if let Err(e) = result {
+		let status_code = match e.downcast_ref::<HttpError>() {
+			Some(HttpError::SlowClient | HttpError::RequestTimeout) => HttpStatusCode::Timeout,
+			Some(e) => e.status_code(),
+			None => HttpStatusCode::BadRequest,
+		};
+		
+		throw_error_and_shutdown(reader.get_mut(), status_code).await;
+		return Err(e);
+	}
+	
+	Ok(())
+}
+
+async fn read_sized_body(reader: &mut BufReader<TcpStream>, req: &mut HttpRequest, content_length: usize, deadline: Instant) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let received = req.body.len();
+	if content_length > received {
+		let mut body_buffer: BytesMut = BytesMut::zeroed(content_length);
+		body_buffer[..received].copy_from_slice(&req.body);
+		read_exact_with_min_rate(reader, &mut body_buffer[received..], deadline).await?;
+		req.set_body(body_buffer.freeze());
 	}
 	
 	Ok(())
 }
 
+/*
+ * Chunked bodies have no length up front, so they are decoded while they arrive, starting
+ * with whatever followed the head in the first read, until the last chunk or MAX_BODY_SIZE.
+ */
+async fn read_chunked_body(reader: &mut BufReader<TcpStream>, req: &mut HttpRequest, deadline: Instant) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let mut buffer: BytesMut = BytesMut::from(&req.take_rest()[..]);
+	let mut decoder = ChunkedDecoder::new(MAX_BODY_SIZE);
+	let mut rate = DataRate::new();
+	
+	while !decoder.decode(&mut buffer)? {
+		read_buf_with_min_rate(reader, &mut buffer, &mut rate, deadline).await?;
+	}
+	
+	let (body, trailers) = decoder.into_parts();
+	req.set_body(body);
+	req.set_trailers(trailers);
+	req.set_rest(buffer.freeze());
+	Ok(())
+}
+
+struct ChallengeSolution {
+	challenge: String,
+	nonce: String,
+}
+
+impl FromQuery for ChallengeSolution {
+	fn from_query(query: &Query) -> Result<Self, QueryError> {
+		Ok(Self { challenge: query.required("challenge")?, nonce: query.required("nonce")? })
+	}
+}
+
+/*
+ * Answers the page's `?challenge=<token>&nonce=<n>` request, handing out the pass
+ * cookie when the solution holds.
+ */
+async fn verify_challenge(stream: &mut TcpStream, info: &ConnectionInfo, solution: Option<ChallengeSolution>) {
+	let cookie = solution.and_then(|solution| Challenge::verify(info.addr.ip(), &solution.challenge, &solution.nonce));
+	
+	match cookie {
+		Some(cookie) => {
+			let res = HttpV10::from_body_with_headers(HttpStatusCode::Ok, "text/plain", "OK", &HeaderMap::from([("Set-Cookie", cookie)]));
+			write_and_shutdown(stream, res.as_bytes()).await;
+		},
+		None => throw_error_and_shutdown(stream, HttpStatusCode::Forbidden).await,
+	}
+}
+
+fn is_slow_client(err: &(dyn Error + 'static)) -> bool {
+	matches!(err.downcast_ref::<HttpError>(), Some(HttpError::SlowClient))
+}
+
 async fn throw_error_and_shutdown(stream: &mut TcpStream, status_code: HttpStatusCode) {
-	if let Ok(_) = stream.write_all(HttpV10::from_status_code(status_code).as_bytes()).await {
+	write_and_shutdown(stream, HttpV10::from_status_code(status_code).as_bytes()).await;
+}
+
+async fn write_and_shutdown(stream: &mut TcpStream, res: &[u8]) {
+	if stream.write_all(res).await.is_ok() {
 		match stream.shutdown().await {
 			Ok(_) => (),
 			Err(e) => {
-				eprintln!("Failed to shutdown stream: {}", e.to_string());
+				eprintln!("Failed to shutdown stream: {}", e);
 			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+async fn throw_service_unavailable(stream: &mut TcpStream) {
+	let retry_after = HeaderMap::from([("Retry-After", RETRY_AFTER_SECS.to_string())]);
+	let res = HttpV10::from_status_code_with_headers(HttpStatusCode::ServiceUnavailable, &retry_after);
+	write_and_shutdown(stream, res.as_bytes()).await;
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+	use tokio::net::TcpListener;
+	use crate::core::GeoInfo;
+	
+	// Each test comes from its own address, so the per-client limits of one do not reach another
+	async fn exchange(client: Ipv4Addr, requests: &str) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let mut stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+		let (server, _) = listener.accept().await.unwrap();
+		
+		stream.write_all(requests.as_bytes()).await.unwrap();
+		let info = ConnectionInfo { addr: SocketAddr::new(IpAddr::V4(client), 40000), geo: GeoInfo::default(), tls_fingerprint: None };
+		let _ = handle(server, info).await;
+		
+		let mut response = String::new();
+		stream.read_to_string(&mut response).await.unwrap();
+		response
+	}
+	
+	// Bodies end without a line break, so a status line starts at any version not following a request target
+	fn status_lines(response: &str) -> Vec<&str> {
+		response.match_indices("HTTP/1.")
+			.filter(|(at, _)| !response[..*at].ends_with(' '))
+			.filter_map(|(at, _)| response[at..].lines().next())
+			.collect()
+	}
+	
+	#[tokio::test]
+	async fn answers_pipelined_requests_in_order() {
+		let response = exchange(
+			Ipv4Addr::new(192, 0, 2, 101),
+			"OPTIONS * HTTP/1.1\r\nHost: a\r\n\r\nGET /missing HTTP/1.1\r\nHost: a\r\n\r\nTRACE / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n",
+		).await;
+		
+		assert_eq!(status_lines(&response), ["HTTP/1.1 200 OK", "HTTP/1.1 404 Not Found", "HTTP/1.1 200 OK"]);
+		let allow = response.find("Allow: ").unwrap();
+		let not_found = response.find("404 Not Found").unwrap();
+		let trace = response.find("message/http").unwrap();
+		assert!(allow < not_found && not_found < trace);
+	}
+	
+	#[tokio::test]
+	async fn refuses_requests_past_the_pipeline_depth() {
+		let requests = "GET / HTTP/1.1\r\nHost: a\r\n\r\n".repeat(MAX_PIPELINE_DEPTH + 2);
+		let response = exchange(Ipv4Addr::new(192, 0, 2, 102), &requests).await;
+		
+		let lines = status_lines(&response);
+		assert_eq!(lines.len(), MAX_PIPELINE_DEPTH + 2);
+		assert!(lines[..=MAX_PIPELINE_DEPTH].iter().all(|line| *line == "HTTP/1.1 404 Not Found"));
+		assert_eq!(lines[MAX_PIPELINE_DEPTH + 1], "HTTP/1.0 503 Service Unavailable");
+	}
+	
+	#[tokio::test]
+	async fn answers_head_without_a_body() {
+		let response = exchange(
+			Ipv4Addr::new(192, 0, 2, 103),
+			"HEAD / HTTP/1.1\r\nHost: a\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n",
+		).await;
+		
+		let (head, rest) = response.split_once("\r\n\r\n").unwrap();
+		assert!(head.starts_with("HTTP/1.1 404 Not Found"));
+		assert!(head.contains("Content-Length: 9"));
+		assert!(rest.starts_with("HTTP/1.1 404 Not Found"));
+		assert!(rest.ends_with("\r\n\r\nNot Found"));
+	}
+	
+	#[tokio::test]
+	async fn answers_http_1_0_head_without_a_body() {
+		let response = exchange(Ipv4Addr::new(192, 0, 2, 104), "HEAD / HTTP/1.0\r\n\r\n").await;
+		
+		assert!(response.starts_with("HTTP/1.0 404 Not Found"));
+		assert!(response.contains("Content-Length: 9"));
+		assert!(response.ends_with("\r\n\r\n"));
+	}
+	
+	#[tokio::test]
+	async fn reflects_trailers_to_clients_that_accept_them() {
+		let request = "TRACE / HTTP/1.1\r\nHost: a\r\nTE: trailers\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n0\r\nDigest: sha-256=x\r\nContent-Length: 5\r\n\r\n";
+		let response = exchange(Ipv4Addr::new(192, 0, 2, 105), request).await;
+		
+		let (head, body) = response.split_once("\r\n\r\n").unwrap();
+		assert!(head.contains("Transfer-Encoding: chunked"));
+		assert!(head.contains("Trailer: Digest"));
+		assert!(!head.contains("Content-Length"));
+		assert!(body.ends_with("\r\n0\r\nDigest: sha-256=x\r\n\r\n"));
+	}
+	
+	#[tokio::test]
+	async fn drops_trailers_for_clients_that_do_not_accept_them() {
+		let request = "TRACE / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nDigest: sha-256=x\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n";
+		let response = exchange(Ipv4Addr::new(192, 0, 2, 106), request).await;
+		
+		assert_eq!(status_lines(&response), ["HTTP/1.1 200 OK", "HTTP/1.1 404 Not Found"]);
+		assert!(!response.contains("Transfer-Encoding: chunked\r\nTrailer"));
+		assert!(!response.contains("Digest: sha-256=x"));
+		assert!(response.contains("Content-Type: message/http\r\nContent-Length: "));
+	}
+}
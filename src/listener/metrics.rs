@@ -0,0 +1,30 @@
+use std::{
+	error::Error,
+	time::Duration
+};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::TcpStream,
+	time::timeout
+};
+use crate::{
+	core::Metrics,
+	enums::{HttpError, HttpStatusCode},
+	protocols::HttpV11,
+	MAX_HEADERS_SIZE
+};
+
+pub async fn handle(mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+	let mut header_buffer: [u8; MAX_HEADERS_SIZE] = [0u8; MAX_HEADERS_SIZE];
+	
+	let bytes_read = timeout(Duration::from_secs(6), stream.read(&mut header_buffer)).await??;
+	if bytes_read == 0 {
+		return Err(Box::new(HttpError::ConnectionClosed));
+	}
+	
+	let res = HttpV11::from_body(HttpStatusCode::Ok, "text/plain; version=0.0.4", &Metrics::render());
+	stream.write_all(res.as_bytes()).await?;
+	stream.shutdown().await?;
+	
+	Ok(())
+}
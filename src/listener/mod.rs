@@ -1,5 +1,10 @@
 mod http;
 mod tls;
+mod metrics;
+mod admin;
 
 pub use http::handle as handle_http_connection;
-pub use tls::handle as handle_tls_connection;
\ No newline at end of file
+pub use http::forbid as forbid_http_connection;
+pub use tls::handle as handle_tls_connection;
+pub use metrics::handle as handle_metrics_connection;
+pub use admin::handle as handle_admin_connection;
\ No newline at end of file
@@ -1,58 +1,103 @@
 use std::{
-	collections::HashMap,
 	error::Error,
-	net::SocketAddr,
 	time::Duration
 };
+use bytes::Bytes;
 use tokio::{
 	io::{AsyncReadExt, AsyncWriteExt, BufReader},
-	time::{timeout, timeout_at, Instant},
+	time::{timeout_at, Instant},
 	net::TcpStream
 };
 use crate::{
-	core::RateLimiter,
-	enums::{HttpError, HttpStatusCode},
+	core::{ConnectionInfo, HeaderMap, HttpRequest, LoadShedder, RateLimiter, Tarpit},
+	enums::{HttpError, HttpStatusCode, RateLimitDecision, RoutePriority},
 	MAX_HEADERS_SIZE,
-	protocols::{HttpV11}
+	RETRY_AFTER_SECS,
+	HEADER_READ_TIMEOUT_SECS,
+	RESPONSE_WRITE_TIMEOUT_SECS,
+	SLOW_CLIENT_PENALTY,
+	protocols::{HttpV11},
+	utils::data_rate::write_all_with_min_rate
 };
 use tokio_rustls::server::TlsStream;
 
-pub async fn handle(stream: TlsStream<TcpStream>, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
-	let ip: String = addr.ip().to_string();
-	RateLimiter::add(ip.clone());
+pub async fn handle(stream: TlsStream<TcpStream>, info: ConnectionInfo) -> Result<(), Box<dyn Error>> {
+	let ip: String = info.addr.ip().to_string();
 	
 	let mut reader: BufReader<TlsStream<TcpStream>> = BufReader::new(stream);
-	if RateLimiter::is_blocked(&ip) {
-		throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::TooManyRequests).await;
-		return Err(Box::new(HttpError::TooManyRequests));
+	// The TLS listener does not serve pages yet, so there is no challenge to hand out here
+	match RateLimiter::check(&info, false).await {
+		RateLimitDecision::Allow | RateLimitDecision::Challenge => (),
+		RateLimitDecision::Reject => {
+			throw_error_and_shutdown(reader.get_mut(), HttpStatusCode::TooManyRequests).await;
+			return Err(Box::new(HttpError::TooManyRequests));
+		},
+		RateLimitDecision::Tarpit => {
+			Tarpit::hold(reader.get_mut(), HttpV11::from_status_code(HttpStatusCode::TooManyRequests, &closing()).as_bytes()).await;
+			return Err(Box::new(HttpError::TooManyRequests));
+		}
 	}
 	
 	let mut header_buffer: [u8; MAX_HEADERS_SIZE] = [0u8; MAX_HEADERS_SIZE];
 	
-	let total_deadline: Instant = Instant::now() + Duration::from_secs(12);
-	let chunk_deadline = Duration::from_secs(6);
+	let header_deadline: Instant = Instant::now() + Duration::from_secs(HEADER_READ_TIMEOUT_SECS);
 	
-	let bytes_read = timeout_at(
-		total_deadline,
-		timeout(chunk_deadline, reader.read(&mut header_buffer))
-	).await???;
+	let bytes_read = timeout_at(header_deadline, reader.read(&mut header_buffer)).await??;
 	
 	if bytes_read == 0 {
 		return Err(Box::new(HttpError::ConnectionClosed));
 	}
 	
-	reader.into_inner().write_all(HttpV11::from_status_code(HttpStatusCode::Ok, HashMap::new()).as_bytes()).await?;
+	// Parsed like on the HTTP listener, so route priorities apply to HTTPS as well
+	let req: HttpRequest = match HttpRequest::new(Bytes::copy_from_slice(&header_buffer[..bytes_read])).await {
+		Ok(req) => req,
+		Err(e) => {
+			throw_error_and_shutdown(reader.get_mut(), e.status_code()).await;
+			return Err(Box::new(e));
+		}
+	};
+	
+	let Some(mut guard) = LoadShedder::acquire(RoutePriority::from_path(req.path())) else {
+		let retry_after = HeaderMap::from([("Retry-After", RETRY_AFTER_SECS.to_string())]);
+		let res = HttpV11::from_status_code_with_headers(HttpStatusCode::ServiceUnavailable, &closing(), &retry_after);
+		if reader.get_mut().write_all(res.as_bytes()).await.is_ok() {
+			let _ = reader.get_mut().shutdown().await;
+		}
+		
+		return Err(Box::new(HttpError::ServiceUnavailable));
+	};
+	
+	guard.start();
+	let res = HttpV11::from_status_code(HttpStatusCode::Ok, &closing());
+	drop(guard);
+	
+	let write_deadline: Instant = Instant::now() + Duration::from_secs(RESPONSE_WRITE_TIMEOUT_SECS);
+	let slow_client = match write_all_with_min_rate(&mut reader.into_inner(), res.as_bytes(), write_deadline).await {
+		Ok(_) => false,
+		Err(e) if matches!(e.downcast_ref::<HttpError>(), Some(HttpError::SlowClient)) => true,
+		Err(e) => return Err(e),
+	};
+	
+	if slow_client {
+		RateLimiter::penalize(&ip, SLOW_CLIENT_PENALTY).await;
+		return Err(Box::new(HttpError::SlowClient));
+	}
 	
 	Ok(())
 }
 
 async fn throw_error_and_shutdown(stream: &mut TlsStream<TcpStream>, status_code: HttpStatusCode) {
-	if let Ok(_) = stream.write_all(HttpV11::from_status_code(status_code, HashMap::new()).as_bytes()).await {
+	if stream.write_all(HttpV11::from_status_code(status_code, &closing()).as_bytes()).await.is_ok() {
 		match stream.shutdown().await {
 			Ok(_) => (),
 			Err(e) => {
-				eprintln!("Failed to shutdown stream: {}", e.to_string());
+				eprintln!("Failed to shutdown stream: {}", e);
 			}
 		}
 	}
+}
+
+/// Stands in for the request headers: every connection here ends after one response.
+fn closing() -> HeaderMap {
+	HeaderMap::from([("Connection", String::from("close"))])
 }
\ No newline at end of file
@@ -1,71 +1,322 @@
 use std::error::Error;
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::io::{Error as IoError, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::os::unix::fs::PermissionsExt;
 use std::sync::Arc;
-use tokio::net::TcpListener;
-use tokio_rustls::TlsAcceptor;
-use crate::core::RateLimiter;
-use crate::listener::{handle_http_connection, handle_tls_connection};
-use crate::utils::helper::load_tls_config;
-
-mod core;
-mod protocols;
-mod traits;
-mod utils;
-mod enums;
-mod listener;
-
-pub const MAX_HEADERS_SIZE: usize = 2048;
-pub const MAX_HEADERS_LENGTH: usize = 25;
-pub const MAX_BODY_SIZE: usize = 2_097_152; // 2MB
-pub const MAX_REQUEST_PER_MINUTE: u8 = 100;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream, UnixListener};
+use tokio::time::timeout;
+use tokio_rustls::{rustls::{server::Acceptor, ServerConfig}, server::TlsStream, LazyConfigAcceptor};
+use rustrate::core::{Challenge, ConnectionInfo, ConnectionLimiter, GeoIp, HandshakeLimiter, IpFilter, RateLimiter, Simulator, TlsFingerprint};
+use rustrate::enums::DenyAction;
+use rustrate::listener::{forbid_http_connection, handle_admin_connection, handle_http_connection, handle_metrics_connection, handle_tls_connection};
+use rustrate::stores::{GossipStore, MemoryStore, RedisStore};
+use rustrate::utils::helper::load_tls_config;
+use rustrate::{CONNECTION_OVERFLOW, DENY_ACTION, MAX_REQUEST_PER_MINUTE, RATE_LIMIT_SNAPSHOT_PATH, TLS_HANDSHAKE_TIMEOUT_SECS};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+	let args: Vec<String> = std::env::args().collect();
+	if args.get(1).map(String::as_str) == Some("simulate") {
+		return simulate(&args[2..]).await;
+	}
+	
 	let (http_listener, tls_listener) = create_listeners().await?;
-	let tls_config = load_tls_config()?;
-	let acceptor = TlsAcceptor::from(Arc::new(tls_config));
-	let acceptor = acceptor.clone();
+	let tls_config = Arc::new(load_tls_config()?);
+	
+	// RUSTRATE_SNAPSHOT_PATH=/var/lib/rustrate/rate_limiter.snapshot moves the counters snapshot
+	let snapshot_path = env_or("RUSTRATE_SNAPSHOT_PATH", RATE_LIMIT_SNAPSHOT_PATH);
+	create_rate_limiter(&snapshot_path).await?;
+	Challenge::init()?;
+	create_metrics_listener().await?;
+	create_admin_listener().await?;
+	create_ip_filter();
+	create_geoip();
+	tokio::spawn(HandshakeLimiter::cleanup());
 	
-	create_rate_limiter_cleaner();
+	let shutdown = shutdown_signal();
+	tokio::pin!(shutdown);
 	
 	loop {
+		let accepting = ConnectionLimiter::is_accepting(CONNECTION_OVERFLOW);
+		
 		tokio::select! {
-			Ok((stream, addr)) = http_listener.accept() => {
-				tokio::spawn(async move {
-					if let Err(err) = handle_http_connection(stream, addr).await {
-						eprintln!("{}", err.to_string());
-					}
-				});
+			_ = &mut shutdown => {
+				match RateLimiter::snapshot(&snapshot_path).await {
+					Ok(count) => println!("Saved {} rate limiter entries", count),
+					Err(err) => eprintln!("Failed to snapshot rate limiter: {}", err),
+				}
+				
+				return Ok(());
 			}
 			
-			Ok((stream, addr)) = tls_listener.accept() => {
-				match acceptor.accept(stream).await {
-					Ok(tls_stream) => {
+			_ = ConnectionLimiter::released(), if !accepting => {}
+			
+			Ok((stream, addr)) = http_listener.accept(), if accepting => {
+				let Some(info) = resolve_client(addr) else {
+					// Answered under the connection limits, so denied clients cannot pile up tasks; past them they are just dropped
+					if DENY_ACTION == DenyAction::Forbidden && let Some(connection) = ConnectionLimiter::try_acquire(addr.ip()) {
 						tokio::spawn(async move {
-							if let Err(err) = handle_tls_connection(tls_stream, addr).await {
-								eprintln!("{}", err.to_string());
-							}
+							let _connection = connection;
+							forbid_http_connection(stream).await;
 						});
 					}
 					
-					Err(err) => {
-						eprintln!("{}", err.to_string());
+					continue;
+				};
+				
+				let Some(connection) = ConnectionLimiter::try_acquire(addr.ip()) else {
+					continue;
+				};
+				
+				tokio::spawn(async move {
+					let _connection = connection;
+					if let Err(err) = handle_http_connection(stream, info).await {
+						eprintln!("{}", err);
 					}
+				});
+			}
+			
+			Ok((stream, addr)) = tls_listener.accept(), if accepting => {
+				let Some(mut info) = resolve_client(addr) else {
+					continue;
+				};
+				
+				let Some(connection) = ConnectionLimiter::try_acquire(addr.ip()) else {
+					continue;
+				};
+				
+				// Only connections that are going to be served use up the handshake budget
+				if !IpFilter::is_allowed(addr.ip()) && !HandshakeLimiter::allow(addr.ip()) {
+					continue;
 				}
+				
+				let tls_config = tls_config.clone();
+				tokio::spawn(async move {
+					let _connection = connection;
+					let handshake_deadline = Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECS);
+					let tls_stream = match timeout(handshake_deadline, accept_tls(stream, tls_config, &mut info)).await {
+						Ok(Ok(tls_stream)) => tls_stream,
+						Ok(Err(err)) => {
+							eprintln!("{}", err);
+							return;
+						},
+						Err(_) => {
+							eprintln!("TLS handshake with {} timed out", addr);
+							return;
+						}
+					};
+					
+					if let Err(err) = handle_tls_connection(tls_stream, info).await {
+						eprintln!("{}", err);
+					}
+				});
 			}
 		}
 	}
 }
 
+/// Locates the client of `addr`, or refuses it for its address or its location. The
+/// denylist is checked first, so clients it denies cost no database lookup.
+fn resolve_client(addr: SocketAddr) -> Option<ConnectionInfo> {
+	if IpFilter::is_denied(addr.ip()) {
+		return None;
+	}
+	
+	let geo = GeoIp::lookup(addr.ip());
+	if GeoIp::is_denied(&geo) {
+		eprintln!("Denied {} from {}", addr, geo);
+		return None;
+	}
+	
+	Some(ConnectionInfo { addr, geo, tls_fingerprint: None })
+}
+
+/// Fingerprints the ClientHello and only goes on with the handshake when the fingerprint
+/// is not denied, so denied clients never get a server flight.
+async fn accept_tls(stream: TcpStream, tls_config: Arc<ServerConfig>, info: &mut ConnectionInfo) -> Result<TlsStream<TcpStream>, Box<dyn Error>> {
+	info.tls_fingerprint = TlsFingerprint::peek(&stream).await;
+	
+	let start = LazyConfigAcceptor::new(Acceptor::default(), stream).await?;
+	if TlsFingerprint::is_denied(info) {
+		return Err(Box::new(IoError::new(ErrorKind::PermissionDenied, format!("TLS fingerprint of {} is denied", info.addr))));
+	}
+	
+	Ok(start.into_stream(tls_config).await?)
+}
+
+/// `rustrate simulate <access log> [--limit <requests per minute>]` replays the log through
+/// the configured policies and prints what they would have blocked, without serving anything.
+async fn simulate(args: &[String]) -> Result<(), Box<dyn Error>> {
+	let usage = "usage: rustrate simulate <access log> [--limit <requests per minute>]";
+	let (path, limit) = match args {
+		[path] => (path, MAX_REQUEST_PER_MINUTE),
+		[path, flag, limit] if flag == "--limit" => (path, limit.parse()?),
+		_ => return Err(usage.into()),
+	};
+	
+	IpFilter::load(std::env::var("RUSTRATE_ALLOWLIST_FILE").ok().as_deref(), std::env::var("RUSTRATE_DENYLIST_FILE").ok().as_deref()).await;
+	GeoIp::load(std::env::var("RUSTRATE_GEOIP_COUNTRY_DB").ok().as_deref(), std::env::var("RUSTRATE_GEOIP_ASN_DB").ok().as_deref()).await;
+	
+	print!("{}", Simulator::replay(path, limit).await?);
+	
+	Ok(())
+}
+
 async fn create_listeners() -> Result<(TcpListener, TcpListener), Box<dyn Error>> {
 	let ipv4 = Ipv4Addr::new(127, 0, 0, 1);
 	
-	let http_listener = TcpListener::bind(SocketAddrV4::new(ipv4, 80)).await?;
-	let tls_listener = TcpListener::bind(SocketAddrV4::new(ipv4, 443)).await?;
+	let http_port: u16 = env_or("RUSTRATE_HTTP_PORT", "80").parse()?;
+	let tls_port: u16 = env_or("RUSTRATE_HTTPS_PORT", "443").parse()?;
+	
+	let http_listener = TcpListener::bind(SocketAddrV4::new(ipv4, http_port)).await?;
+	let tls_listener = TcpListener::bind(SocketAddrV4::new(ipv4, tls_port)).await?;
 	
 	Ok((http_listener, tls_listener))
 }
 
-fn create_rate_limiter_cleaner() {
+async fn create_rate_limiter(snapshot_path: &str) -> Result<(), Box<dyn Error>> {
+	// RUSTRATE_REDIS_ADDR=127.0.0.1:6379 shares the counters through redis
+	// RUSTRATE_GOSSIP_ADDR=127.0.0.1:7946 RUSTRATE_GOSSIP_PEERS=127.0.0.1:7947,... shares them between peers,
+	// signing every datagram with RUSTRATE_GOSSIP_KEY, as RUSTRATE_GOSSIP_NODE_ID (random by default)
+	if let Ok(addr) = std::env::var("RUSTRATE_REDIS_ADDR") {
+		RateLimiter::init(Box::new(RedisStore::new(addr)));
+	} else if let Ok(addr) = std::env::var("RUSTRATE_GOSSIP_ADDR") {
+		let peers: Vec<SocketAddr> = env_or("RUSTRATE_GOSSIP_PEERS", "")
+			.split(',')
+			.filter(|peer| !peer.trim().is_empty())
+			.map(|peer| peer.trim().parse())
+			.collect::<Result<_, _>>()?;
+		
+		let key = match std::env::var("RUSTRATE_GOSSIP_KEY") {
+			Ok(key) if !key.is_empty() => key.into_bytes(),
+			_ => return Err("RUSTRATE_GOSSIP_KEY is required to gossip with peers".into()),
+		};
+		
+		let node_id = std::env::var("RUSTRATE_GOSSIP_NODE_ID").ok();
+		RateLimiter::init(Box::new(GossipStore::bind(addr.parse()?, peers, key, node_id).await?));
+	} else {
+		RateLimiter::init(Box::new(MemoryStore::new()));
+	}
+	
+	match RateLimiter::restore(snapshot_path).await {
+		Ok(count) => println!("Restored {} rate limiter entries", count),
+		Err(err) => eprintln!("Failed to restore rate limiter: {}", err),
+	}
+	
 	tokio::spawn(RateLimiter::cleanup());
+	tokio::spawn(RateLimiter::persist(String::from(snapshot_path)));
+	
+	Ok(())
+}
+
+async fn create_metrics_listener() -> Result<(), Box<dyn Error>> {
+	// RUSTRATE_METRICS_ADDR=127.0.0.1:9100 serves the Prometheus metrics on a separate port
+	let Ok(addr) = std::env::var("RUSTRATE_METRICS_ADDR") else {
+		return Ok(());
+	};
+	
+	let listener = TcpListener::bind(addr).await?;
+	tokio::spawn(async move {
+		loop {
+			if let Ok((stream, _)) = listener.accept().await {
+				tokio::spawn(async move {
+					if let Err(err) = handle_metrics_connection(stream).await {
+						eprintln!("{}", err);
+					}
+				});
+			}
+		}
+	});
+	
+	Ok(())
+}
+
+async fn create_admin_listener() -> Result<(), Box<dyn Error>> {
+	// RUSTRATE_ADMIN_ADDR=127.0.0.1:9200 or unix:/run/rustrate/admin.sock, guarded by RUSTRATE_ADMIN_TOKEN
+	let Ok(addr) = std::env::var("RUSTRATE_ADMIN_ADDR") else {
+		return Ok(());
+	};
+	
+	let token: Arc<str> = match std::env::var("RUSTRATE_ADMIN_TOKEN") {
+		Ok(token) if !token.is_empty() => Arc::from(token),
+		_ => return Err("RUSTRATE_ADMIN_TOKEN is required to serve the admin API".into()),
+	};
+	
+	if let Some(path) = addr.strip_prefix("unix:") {
+		// A socket left behind by a previous run would make the bind fail
+		let _ = std::fs::remove_file(path);
+		let listener = UnixListener::bind(path)?;
+		std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+		
+		tokio::spawn(async move {
+			loop {
+				if let Ok((stream, _)) = listener.accept().await {
+					let token = token.clone();
+					tokio::spawn(async move {
+						if let Err(err) = handle_admin_connection(stream, &token).await {
+							eprintln!("{}", err);
+						}
+					});
+				}
+			}
+		});
+		
+		return Ok(());
+	}
+	
+	let addr: SocketAddr = addr.parse()?;
+	if !addr.ip().is_loopback() {
+		return Err(format!("Admin API must listen on a loopback address, not {}", addr).into());
+	}
+	
+	let listener = TcpListener::bind(addr).await?;
+	tokio::spawn(async move {
+		loop {
+			if let Ok((stream, _)) = listener.accept().await {
+				let token = token.clone();
+				tokio::spawn(async move {
+					if let Err(err) = handle_admin_connection(stream, &token).await {
+						eprintln!("{}", err);
+					}
+				});
+			}
+		}
+	});
+	
+	Ok(())
+}
+
+fn create_ip_filter() {
+	let allowlist_path = std::env::var("RUSTRATE_ALLOWLIST_FILE").ok();
+	let denylist_path = std::env::var("RUSTRATE_DENYLIST_FILE").ok();
+	
+	tokio::spawn(IpFilter::watch(allowlist_path, denylist_path));
+}
+
+fn create_geoip() {
+	let country_path = std::env::var("RUSTRATE_GEOIP_COUNTRY_DB").ok();
+	let asn_path = std::env::var("RUSTRATE_GEOIP_ASN_DB").ok();
+	
+	tokio::spawn(GeoIp::watch(country_path, asn_path));
+}
+
+async fn shutdown_signal() {
+	let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+		Ok(signal) => signal,
+		Err(err) => {
+			eprintln!("Failed to listen for SIGTERM: {}", err);
+			let _ = tokio::signal::ctrl_c().await;
+			return;
+		}
+	};
+	
+	tokio::select! {
+		_ = tokio::signal::ctrl_c() => (),
+		_ = terminate.recv() => (),
+	}
+}
+
+fn env_or(name: &str, default: &str) -> String {
+	std::env::var(name).unwrap_or_else(|_| String::from(default))
 }
\ No newline at end of file
@@ -1,26 +1,51 @@
 use std::error::Error;
-use crate::core::{HttpRequest};
-use crate::enums::HttpStatusCode;
+use crate::core::{HeaderMap, HttpRequest};
+use crate::enums::{HttpMethod, HttpStatusCode};
 use crate::traits::HttpProtocol;
 
 pub struct HttpV10;
 
 impl HttpProtocol for HttpV10 {
-	async fn handle(_: HttpRequest) -> Result<Vec<u8>, Box<dyn Error>> {
-		let res = Self::from_status_code(HttpStatusCode::NotFound).into_bytes();
-		Ok(res)
+	async fn handle(req: HttpRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+		let res = match req.method {
+			HttpMethod::Options => Self::respond(&req, HttpStatusCode::Ok, "text/plain", HttpStatusCode::Ok.reason(), &HeaderMap::from([("Allow", HttpMethod::allowed())])),
+			HttpMethod::Trace => Self::respond(&req, HttpStatusCode::Ok, "message/http", &req.trace_message(), &HeaderMap::new()),
+			_ => Self::respond(&req, HttpStatusCode::NotFound, "text/plain", HttpStatusCode::NotFound.reason(), &HeaderMap::new()),
+		};
+		
+		Ok(res.into_bytes())
 	}
 }
 
 impl HttpV10 {
 	pub fn from_status_code(status: HttpStatusCode) -> String {
+		Self::from_status_code_with_headers(status, &HeaderMap::new())
+	}
+	
+	pub fn from_status_code_with_headers(status: HttpStatusCode, extra_headers: &HeaderMap) -> String {
 		let body = status.reason();
+		Self::message(status, "text/plain", body, extra_headers, true)
+	}
+	
+	pub fn from_body_with_headers(status: HttpStatusCode, content_type: &str, body: &str, extra_headers: &HeaderMap) -> String {
+		Self::message(status, content_type, body, extra_headers, true)
+	}
+	
+	/// Answers `req` with `body`. HEAD gets the fields GET would, its length included,
+	/// but never the body: the client would read it as the next response.
+	pub fn respond(req: &HttpRequest, status: HttpStatusCode, content_type: &str, body: &str, extra_headers: &HeaderMap) -> String {
+		Self::message(status, content_type, body, extra_headers, req.method != HttpMethod::Head)
+	}
+	
+	fn message(status: HttpStatusCode, content_type: &str, body: &str, extra_headers: &HeaderMap, with_body: bool) -> String {
 		format!(
-			"HTTP/1.0 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\nServer: RustRate/1.0.0\r\n\r\n{}",
+			"HTTP/1.0 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n{}Server: RustRate/1.0.0\r\n\r\n{}",
 			status.code(),
-			body,
+			status.reason(),
+			content_type,
 			body.len(),
-			body
+			extra_headers,
+			if with_body { body } else { "" }
 		)
 	}
 }
\ No newline at end of file
@@ -1,7 +1,6 @@
-use std::collections::HashMap;
 use std::error::Error;
-use crate::core::{HttpRequest};
-use crate::enums::HttpStatusCode;
+use crate::core::{ChunkedDecoder, HeaderMap, HttpRequest};
+use crate::enums::{HttpMethod, HttpStatusCode};
 use crate::traits::HttpProtocol;
 use crate::utils::helper::http_date_string;
 
@@ -9,23 +8,101 @@ pub struct HttpV11;
 
 impl HttpProtocol for HttpV11 {
 	async fn handle(req: HttpRequest) -> Result<Vec<u8>, Box<dyn Error>> {
-		let res = Self::from_status_code(HttpStatusCode::NotFound, req.headers).into_bytes();
-		Ok(res)
+		let res = match req.method {
+			HttpMethod::Options => Self::respond(&req, HttpStatusCode::Ok, "text/plain", HttpStatusCode::Ok.reason(), &HeaderMap::from([("Allow", HttpMethod::allowed())])),
+			// TRACE reflects the request's trailers along with its head
+			HttpMethod::Trace => Self::respond_with_trailers(&req, HttpStatusCode::Ok, "message/http", &req.trace_message(), req.trailers()),
+			_ => Self::respond(&req, HttpStatusCode::NotFound, "text/plain", HttpStatusCode::NotFound.reason(), &HeaderMap::new()),
+		};
+		
+		Ok(res.into_bytes())
 	}
 }
 
 impl HttpV11 {
-	pub fn from_status_code(status: HttpStatusCode, headers: HashMap<String, String>) -> String {
-		let keep_alive: bool = headers.get("Connection").map(|v| v.eq_ignore_ascii_case("keep-alive")).unwrap_or(false);
-		let connection_header = if keep_alive == true { "keep-alive" } else { "close" };
+	pub fn from_status_code(status: HttpStatusCode, headers: &HeaderMap) -> String {
+		Self::from_status_code_with_headers(status, headers, &HeaderMap::new())
+	}
+	
+	pub fn from_status_code_with_headers(status: HttpStatusCode, headers: &HeaderMap, extra_headers: &HeaderMap) -> String {
 		let body = status.reason();
+		Self::message(status, headers, "text/plain", body, extra_headers, true)
+	}
+	
+	/// Answers `req` with `body`. HEAD gets the fields GET would, its length included,
+	/// but never the body: the client would read it as the next response.
+	pub fn respond(req: &HttpRequest, status: HttpStatusCode, content_type: &str, body: &str, extra_headers: &HeaderMap) -> String {
+		Self::message(status, &req.headers, content_type, body, extra_headers, req.method != HttpMethod::Head)
+	}
+	
+	fn message(status: HttpStatusCode, headers: &HeaderMap, content_type: &str, body: &str, extra_headers: &HeaderMap, with_body: bool) -> String {
+		format!(
+			"HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: {}\r\nDate: {}\r\n{}Server: RustRate/1.0.0\r\n\r\n{}",
+			status.code(),
+			status.reason(),
+			content_type,
+			body.len(),
+			if Self::keep_alive(headers) { "keep-alive" } else { "close" },
+			http_date_string(),
+			extra_headers,
+			if with_body { body } else { "" }
+		)
+	}
+	
+	/// Answers `req` with `body` followed by `trailers`, sent chunked since only the chunked
+	/// coding can carry them. Fields a trailer may not carry are dropped, the same ones
+	/// refused in requests. Without `TE: trailers` from the client, or for HEAD, the
+	/// trailers go and the response is the one `respond` builds.
+	pub fn respond_with_trailers(req: &HttpRequest, status: HttpStatusCode, content_type: &str, body: &str, trailers: &HeaderMap) -> String {
+		let mut allowed = HeaderMap::new();
+		let mut names: Vec<&str> = Vec::new();
+		for (name, value) in trailers.iter().filter(|(name, _)| ChunkedDecoder::allows_trailer(name.as_bytes())) {
+			allowed.append(name, value);
+			if !names.iter().any(|known| known.eq_ignore_ascii_case(name)) {
+				names.push(name);
+			}
+		}
 		
+		if req.method == HttpMethod::Head || !Self::accepts_trailers(&req.headers) || names.is_empty() {
+			return Self::respond(req, status, content_type, body, &HeaderMap::new());
+		}
+		
+		let chunk = if body.is_empty() { String::new() } else { format!("{:x}\r\n{}\r\n", body.len(), body) };
+		format!(
+			"HTTP/1.1 {} {}\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nTrailer: {}\r\nConnection: {}\r\nDate: {}\r\nServer: RustRate/1.0.0\r\n\r\n{}0\r\n{}\r\n",
+			status.code(),
+			status.reason(),
+			content_type,
+			names.join(", "),
+			if Self::keep_alive(&req.headers) { "keep-alive" } else { "close" },
+			http_date_string(),
+			chunk,
+			allowed
+		)
+	}
+	
+	/// Whether the request's `TE` lists `trailers`, parameters aside (RFC 9110 section 10.1.4).
+	pub fn accepts_trailers(headers: &HeaderMap) -> bool {
+		headers.get_all("TE").flat_map(|value| value.split(',')).any(|coding| coding.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case("trailers"))
+	}
+	
+	/// HTTP/1.1 connections persist unless the request asks to close (RFC 9112 section 9.3).
+	pub fn keep_alive(headers: &HeaderMap) -> bool {
+		!headers.get_all("Connection").flat_map(|value| value.split(',')).any(|option| option.trim().eq_ignore_ascii_case("close"))
+	}
+	
+	/// Interim response, sent ahead of the final one without ending the exchange.
+	pub fn interim(status: HttpStatusCode) -> String {
+		format!("HTTP/1.1 {} {}\r\n\r\n", status.code(), status.reason())
+	}
+	
+	pub fn from_body(status: HttpStatusCode, content_type: &str, body: &str) -> String {
 		format!(
-			"HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: {}\r\nDate: {}\r\nServer: RustRate/1.0.0\r\n\r\n{}",
+			"HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\nDate: {}\r\nServer: RustRate/1.0.0\r\n\r\n{}",
 			status.code(),
-			body,
+			status.reason(),
+			content_type,
 			body.len(),
-			connection_header,
 			http_date_string(),
 			body
 		)
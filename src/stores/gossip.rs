@@ -0,0 +1,600 @@
+use std::{
+	collections::HashMap,
+	io::{Error as IoError, ErrorKind, Result as IoResult},
+	net::SocketAddr,
+	sync::{Arc, RwLock},
+	time::{Duration, SystemTime, UNIX_EPOCH}
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::{
+	net::UdpSocket,
+	time::interval
+};
+use crate::{
+	traits::{RateLimitStore, StoreBan, StoreEntry, StoreFuture},
+	utils::helper::{decode_hex, encode_hex, random_bytes},
+	RATE_LIMIT_WINDOW_SECS
+};
+
+const GOSSIP_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_DATAGRAM_SIZE: usize = 1200;
+// Hex HMAC-SHA256 line heading every datagram
+const SIGNATURE_SIZE: usize = 65;
+const PROTOCOL_HEADER: &str = "rustrate-gossip/1";
+
+/// G-counter of a single key: one monotonically growing count per node, all of them
+/// belonging to the same fixed window.
+struct Counter {
+	window: u64,
+	counts: HashMap<String, u32>,
+	dirty: bool,
+}
+
+impl Counter {
+	fn total(&self) -> u8 {
+		let total: u32 = self.counts.values().fold(0, |acc, count| acc.saturating_add(*count));
+		total.min(u8::MAX as u32) as u8
+	}
+}
+
+/// Last-writer-wins register of a ban, in unix milliseconds: the latest decision about
+/// a key wins on every node, so a lifted ban (ending when it was lifted) spreads like a new one.
+#[derive(Clone, Copy)]
+struct Ban {
+	until: u64,
+	stamp: u64,
+}
+
+impl Ban {
+	fn is_live(&self, now: u64) -> bool {
+		self.until > now
+	}
+}
+
+struct GossipState {
+	node_id: String,
+	peers: Vec<SocketAddr>,
+	key: Vec<u8>,
+	socket: UdpSocket,
+	counters: RwLock<HashMap<String, Counter>>,
+	bans: RwLock<HashMap<String, Ban>>,
+}
+
+/// Shares approximate counters between instances without a central backend.
+///
+/// Every node counts its own hits per key and per wall-clock window, periodically
+/// sends the keys it touched to its peers over UDP and merges what it receives by
+/// taking the per-node maximum. Lost or duplicated datagrams are therefore harmless,
+/// and a node's decision is based on the sum of all counts it has heard of.
+///
+/// Bans are few, so every node resends all it knows of each round, lifted ones included
+/// until the window ends, and keeps the most recent decision about each key.
+///
+/// Counts and bans only come from the configured peers, in datagrams signed with the
+/// key they all share, so no other sender can raise or claim the count of a node.
+///
+/// Unlike `MemoryStore` and `RedisStore`, whose counters live for a window after their
+/// last hit, counters here are per fixed wall-clock window, since nodes only agree on
+/// a count when they count over the same span. A client sending steadily is cut off
+/// by the other stores once its total crosses the limit, here only once the hits of a
+/// single window do.
+pub struct GossipStore {
+	state: Arc<GossipState>,
+}
+
+impl GossipStore {
+	/// Binds the gossip socket. Without a `node_id`, a random one is drawn: counts a
+	/// previous run of the node sent under another id still count until the window ends.
+	pub async fn bind(addr: SocketAddr, peers: Vec<SocketAddr>, key: Vec<u8>, node_id: Option<String>) -> IoResult<Self> {
+		// Neither the wildcard nor a port shared behind NAT tells nodes apart
+		let node_id = match node_id {
+			Some(node_id) if node_id.is_empty() || node_id.contains(char::is_whitespace) => {
+				return Err(IoError::new(ErrorKind::InvalidInput, format!("Invalid gossip node id {:?}", node_id)));
+			},
+			Some(node_id) => node_id,
+			None => encode_hex(&random_bytes(8)?),
+		};
+		
+		let socket = UdpSocket::bind(addr).await?;
+		let state = Arc::new(GossipState {
+			node_id,
+			peers,
+			key,
+			socket,
+			counters: RwLock::new(HashMap::new()),
+			bans: RwLock::new(HashMap::new()),
+		});
+		
+		tokio::spawn(Self::broadcast(state.clone()));
+		tokio::spawn(Self::receive(state.clone()));
+		
+		Ok(Self { state })
+	}
+	
+	async fn broadcast(state: Arc<GossipState>) {
+		let mut ticker = interval(GOSSIP_INTERVAL);
+		loop {
+			ticker.tick().await;
+			
+			for datagram in Self::collect_deltas(&state) {
+				for peer in &state.peers {
+					if let Err(e) = state.socket.send_to(datagram.as_bytes(), peer).await {
+						eprintln!("Failed to gossip with {}: {}", peer, e);
+					}
+				}
+			}
+		}
+	}
+	
+	async fn receive(state: Arc<GossipState>) {
+		let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+		loop {
+			match state.socket.recv_from(&mut buffer).await {
+				Ok((n, source)) => {
+					if !state.peers.contains(&source) {
+						continue;
+					}
+					
+					if let Some(datagram) = Self::verify(&state.key, &buffer[..n]) {
+						Self::merge(&state, datagram);
+					}
+				},
+				Err(e) => {
+					eprintln!("Failed to receive gossip: {}", e);
+				}
+			}
+		}
+	}
+	
+	/*
+	 * Datagram layout, one entry per line:
+	 * <hex HMAC-SHA256 of the rest of the datagram>
+	 * rustrate-gossip/1 <node id> <window>
+	 * <key> <count of the node for that key>
+	 * ban <key> <end of the ban> <time of the decision>
+	 */
+	fn collect_deltas(state: &GossipState) -> Vec<String> {
+		let window = current_window();
+		let mut lines: Vec<String> = Vec::new();
+		
+		if let Ok(mut counters) = state.counters.write() {
+			for (key, counter) in counters.iter_mut() {
+				if !counter.dirty || counter.window != window {
+					continue;
+				}
+				
+				counter.dirty = false;
+				let count = counter.counts.get(&state.node_id).copied().unwrap_or(0);
+				lines.push(format!("{} {}\n", key, count));
+			}
+		}
+		
+		if let Ok(bans) = state.bans.read() {
+			lines.extend(bans.iter().map(|(key, ban)| format!("ban {} {} {}\n", key, ban.until, ban.stamp)));
+		}
+		
+		let header = format!("{} {} {}\n", PROTOCOL_HEADER, state.node_id, window);
+		let mut datagrams: Vec<String> = Vec::new();
+		let mut datagram = header.clone();
+		for line in lines {
+			if SIGNATURE_SIZE + datagram.len() + line.len() > MAX_DATAGRAM_SIZE {
+				datagrams.push(Self::sign(&state.key, &datagram));
+				datagram = header.clone();
+			}
+			
+			datagram.push_str(&line);
+		}
+		
+		if datagram.len() > header.len() {
+			datagrams.push(Self::sign(&state.key, &datagram));
+		}
+		
+		datagrams
+	}
+	
+	fn sign(key: &[u8], datagram: &str) -> String {
+		let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+		mac.update(datagram.as_bytes());
+		
+		format!("{}\n{}", encode_hex(&mac.finalize().into_bytes()), datagram)
+	}
+	
+	/// The datagram without its signature line, `None` when it was not signed with `key`.
+	fn verify<'a>(key: &[u8], datagram: &'a [u8]) -> Option<&'a str> {
+		let (signature, datagram) = std::str::from_utf8(datagram).ok()?.split_once('\n')?;
+		let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+		mac.update(datagram.as_bytes());
+		mac.verify_slice(&decode_hex(signature)?).ok()?;
+		
+		Some(datagram)
+	}
+	
+	fn merge(state: &GossipState, datagram: &str) {
+		let mut lines = datagram.lines();
+		let (node_id, window): (&str, u64) = match lines.next().map(|line| line.split(' ').collect::<Vec<&str>>()).as_deref() {
+			Some([PROTOCOL_HEADER, node_id, window]) => match window.parse() {
+				Ok(window) => (node_id, window),
+				Err(_) => return,
+			},
+			_ => return,
+		};
+		
+		// Counts of past windows are stale, and a future window would never be cleaned up
+		if node_id == state.node_id || window != current_window() {
+			return;
+		}
+		
+		if let Ok(mut counters) = state.counters.write() {
+			for line in lines {
+				let (key, count) = match line.split(' ').collect::<Vec<&str>>()[..] {
+					[key, count] => (key, count),
+					["ban", key, until, stamp] => {
+						if let (Ok(until), Ok(stamp)) = (until.parse(), stamp.parse()) {
+							Self::merge_ban(state, key, Ban { until, stamp });
+						}
+						
+						continue;
+					},
+					_ => continue,
+				};
+				
+				let Ok(count) = count.parse::<u32>() else { continue };
+				
+				let counter = counters.entry(String::from(key)).or_insert_with(|| Counter {
+					window,
+					counts: HashMap::new(),
+					dirty: false,
+				});
+				
+				if counter.window != window {
+					counter.window = window;
+					counter.counts.clear();
+				}
+				
+				let known = counter.counts.entry(String::from(node_id)).or_insert(0);
+				*known = (*known).max(count);
+			}
+		}
+	}
+	
+	fn merge_ban(state: &GossipState, key: &str, ban: Ban) {
+		if let Ok(mut bans) = state.bans.write() {
+			let known = bans.entry(String::from(key)).or_insert(ban);
+			if (ban.stamp, ban.until) > (known.stamp, known.until) {
+				*known = ban;
+			}
+		}
+	}
+	
+	fn add_sync(&self, key: &str, hits: u8) -> u8 {
+		let window = current_window();
+		if let Ok(mut counters) = self.state.counters.write() {
+			let counter = counters.entry(String::from(key)).or_insert_with(|| Counter {
+				window,
+				counts: HashMap::new(),
+				dirty: false,
+			});
+			
+			if counter.window != window {
+				counter.window = window;
+				counter.counts.clear();
+			}
+			
+			let own = counter.counts.entry(self.state.node_id.clone()).or_insert(0);
+			*own = own.saturating_add(hits as u32);
+			counter.dirty = true;
+			
+			return counter.total();
+		}
+		
+		0
+	}
+	
+	fn get_sync(&self, key: &str) -> Option<u8> {
+		let window = current_window();
+		if let Ok(counters) = self.state.counters.read() && let Some(counter) = counters.get(key) && counter.window == window {
+			return Some(counter.total());
+		}
+		
+		None
+	}
+	
+	fn entries_sync(&self) -> Vec<(String, u8)> {
+		let window = current_window();
+		let Ok(counters) = self.state.counters.read() else {
+			return Vec::new();
+		};
+		
+		counters
+			.iter()
+			.filter(|(_, counter)| counter.window == window)
+			.map(|(key, counter)| (key.clone(), counter.total()))
+			.collect()
+	}
+	
+	fn remove_sync(&self, key: &str) {
+		if let Ok(mut counters) = self.state.counters.write() {
+			counters.remove(key);
+		}
+	}
+	
+	fn cleanup_sync(&self) {
+		let window = current_window();
+		if let Ok(mut counters) = self.state.counters.write() {
+			counters.retain(|_, counter| counter.window == window);
+		}
+		
+		// Lifted bans are kept for a window, long enough to reach every peer
+		let now = millis(SystemTime::now());
+		if let Ok(mut bans) = self.state.bans.write() {
+			bans.retain(|_, ban| ban.is_live(now) || ban.stamp + RATE_LIMIT_WINDOW_SECS * 1000 > now);
+		}
+	}
+	
+	fn ban_sync(&self, key: &str, until: SystemTime) {
+		let now = millis(SystemTime::now());
+		if let Ok(mut bans) = self.state.bans.write() {
+			bans.insert(String::from(key), Ban { until: millis(until), stamp: now });
+		}
+	}
+	
+	fn unban_sync(&self, key: &str) -> bool {
+		let now = millis(SystemTime::now());
+		let Ok(mut bans) = self.state.bans.write() else {
+			return false;
+		};
+		
+		match bans.get_mut(key) {
+			Some(ban) if ban.is_live(now) => {
+				*ban = Ban { until: now, stamp: now };
+				true
+			},
+			_ => false,
+		}
+	}
+	
+	fn ban_ends_sync(&self, keys: &[String]) -> Vec<Option<SystemTime>> {
+		let now = millis(SystemTime::now());
+		let Ok(bans) = self.state.bans.read() else {
+			return vec![None; keys.len()];
+		};
+		
+		keys
+			.iter()
+			.map(|key| bans.get(key).filter(|ban| ban.is_live(now)).map(|ban| UNIX_EPOCH + Duration::from_millis(ban.until)))
+			.collect()
+	}
+	
+	fn bans_sync(&self) -> Vec<StoreBan> {
+		let now = millis(SystemTime::now());
+		let Ok(bans) = self.state.bans.read() else {
+			return Vec::new();
+		};
+		
+		bans
+			.iter()
+			.filter(|(_, ban)| ban.is_live(now))
+			.map(|(key, ban)| StoreBan { key: key.clone(), until: UNIX_EPOCH + Duration::from_millis(ban.until) })
+			.collect()
+	}
+	
+	/// This node's own counts of the current window: the peers hold theirs, and send
+	/// them again as they go on counting.
+	fn snapshot_sync(&self) -> Vec<StoreEntry> {
+		let window = current_window();
+		let now = SystemTime::now();
+		let Ok(counters) = self.state.counters.read() else {
+			return Vec::new();
+		};
+		
+		counters
+			.iter()
+			.filter(|(_, counter)| counter.window == window)
+			.filter_map(|(key, counter)| {
+				let count = *counter.counts.get(&self.state.node_id)?;
+				Some(StoreEntry { key: key.clone(), count: count.min(u8::MAX as u32) as u8, last_seen: now })
+			})
+			.collect()
+	}
+	
+	fn restore_sync(&self, entries: Vec<StoreEntry>) {
+		let window = current_window();
+		let Ok(mut counters) = self.state.counters.write() else {
+			return;
+		};
+		
+		for entry in entries {
+			if window_of(entry.last_seen) != window {
+				continue;
+			}
+			
+			let counter = counters.entry(entry.key).or_insert_with(|| Counter {
+				window,
+				counts: HashMap::new(),
+				dirty: false,
+			});
+			
+			let own = counter.counts.entry(self.state.node_id.clone()).or_insert(0);
+			*own = (*own).max(entry.count as u32);
+			counter.dirty = true;
+		}
+	}
+}
+
+impl RateLimitStore for GossipStore {
+	fn add<'a>(&'a self, key: &'a str, hits: u8) -> StoreFuture<'a, u8> {
+		Box::pin(async move { Ok(self.add_sync(key, hits)) })
+	}
+	
+	fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<u8>> {
+		Box::pin(async move { Ok(self.get_sync(key)) })
+	}
+	
+	/// Only forgets what this node knows: peers still hitting the key gossip their
+	/// counts back until the window ends.
+	fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.remove_sync(key);
+			Ok(())
+		})
+	}
+	
+	fn entries(&self) -> StoreFuture<'_, Vec<(String, u8)>> {
+		Box::pin(async move { Ok(self.entries_sync()) })
+	}
+	
+	fn cleanup(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			self.cleanup_sync();
+			Ok(())
+		})
+	}
+	
+	fn ban<'a>(&'a self, key: &'a str, until: SystemTime) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.ban_sync(key, until);
+			Ok(())
+		})
+	}
+	
+	fn unban<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool> {
+		Box::pin(async move { Ok(self.unban_sync(key)) })
+	}
+	
+	fn ban_ends<'a>(&'a self, keys: &'a [String]) -> StoreFuture<'a, Vec<Option<SystemTime>>> {
+		Box::pin(async move { Ok(self.ban_ends_sync(keys)) })
+	}
+	
+	fn bans(&self) -> StoreFuture<'_, Vec<StoreBan>> {
+		Box::pin(async move { Ok(self.bans_sync()) })
+	}
+	
+	fn snapshot(&self) -> Vec<StoreEntry> {
+		self.snapshot_sync()
+	}
+	
+	fn restore(&self, entries: Vec<StoreEntry>) {
+		self.restore_sync(entries);
+	}
+	
+	fn snapshot_bans(&self) -> Vec<StoreBan> {
+		self.bans_sync()
+	}
+	
+	/// Restored as the oldest decisions, so whatever the peers decided meanwhile wins.
+	fn restore_bans(&self, bans: Vec<StoreBan>) {
+		for ban in bans {
+			Self::merge_ban(&self.state, &ban.key, Ban { until: millis(ban.until), stamp: 0 });
+		}
+	}
+}
+
+fn current_window() -> u64 {
+	window_of(SystemTime::now())
+}
+
+fn window_of(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / RATE_LIMIT_WINDOW_SECS
+}
+
+fn millis(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const KEY: &[u8] = b"shared key";
+	
+	fn datagram(node_id: &str, window: u64, entries: &str) -> String {
+		format!("{} {} {}\n{}", PROTOCOL_HEADER, node_id, window, entries)
+	}
+	
+	#[tokio::test]
+	async fn merges_only_the_current_window() {
+		let store = GossipStore::bind("127.0.0.1:0".parse().unwrap(), Vec::new(), KEY.to_vec(), None).await.unwrap();
+		let window = current_window();
+		
+		GossipStore::merge(&store.state, &datagram("10.0.0.2:7946", window - 1, "stale 50\n"));
+		GossipStore::merge(&store.state, &datagram("10.0.0.2:7946", window + 1, "future 50\n"));
+		assert_eq!(store.get_sync("stale"), None);
+		assert_eq!(store.state.counters.read().unwrap().len(), 0);
+		
+		GossipStore::merge(&store.state, &datagram("10.0.0.2:7946", window, "key 5\n"));
+		GossipStore::merge(&store.state, &datagram("10.0.0.3:7946", window, "key 3\n"));
+		GossipStore::merge(&store.state, &datagram("10.0.0.2:7946", window, "key 4\n"));
+		assert_eq!(store.add_sync("key", 1), 9);
+		
+		// Only this node's share is snapshotted, the peers resend theirs
+		let snapshot = store.snapshot_sync();
+		assert_eq!(snapshot.iter().map(|entry| (entry.key.as_str(), entry.count)).collect::<Vec<_>>(), [("key", 1)]);
+	}
+	
+	#[tokio::test]
+	async fn drops_counters_of_other_windows() {
+		let store = GossipStore::bind("127.0.0.1:0".parse().unwrap(), Vec::new(), KEY.to_vec(), None).await.unwrap();
+		store.add_sync("old", 1);
+		store.add_sync("future", 1);
+		if let Ok(mut counters) = store.state.counters.write() {
+			counters.get_mut("old").unwrap().window -= 1;
+			counters.get_mut("future").unwrap().window += 1;
+		}
+		
+		store.cleanup_sync();
+		assert_eq!(store.state.counters.read().unwrap().len(), 0);
+	}
+	
+	#[tokio::test]
+	async fn keeps_the_latest_ban_decision() {
+		let store = GossipStore::bind("127.0.0.1:0".parse().unwrap(), Vec::new(), KEY.to_vec(), None).await.unwrap();
+		let window = current_window();
+		let now = millis(SystemTime::now());
+		let keys = [String::from("10.0.0.9")];
+		
+		GossipStore::merge(&store.state, &datagram("10.0.0.2:7946", window, &format!("ban 10.0.0.9 {} {}\n", now + 60_000, now - 10)));
+		assert!(store.ban_ends_sync(&keys)[0].is_some());
+		
+		// Lifted on a peer after it was set, and a stale ban heard late changes nothing
+		GossipStore::merge(&store.state, &datagram("10.0.0.3:7946", window, &format!("ban 10.0.0.9 {} {}\n", now - 5, now - 5)));
+		GossipStore::merge(&store.state, &datagram("10.0.0.2:7946", window, &format!("ban 10.0.0.9 {} {}\n", now + 60_000, now - 10)));
+		assert!(store.ban_ends_sync(&keys)[0].is_none());
+		
+		store.ban_sync("10.0.0.9", SystemTime::now() + Duration::from_secs(60));
+		assert!(store.ban_ends_sync(&keys)[0].is_some());
+		assert!(store.unban_sync("10.0.0.9"));
+		assert!(!store.unban_sync("10.0.0.9"));
+		
+		// The lifted ban is still sent, so the peers lift it as well
+		let datagrams = GossipStore::collect_deltas(&store.state);
+		assert!(datagrams.iter().any(|datagram| datagram.contains("ban 10.0.0.9 ")));
+	}
+	
+	#[tokio::test]
+	async fn tells_nodes_on_the_same_wildcard_address_apart() {
+		let first = GossipStore::bind("0.0.0.0:0".parse().unwrap(), Vec::new(), KEY.to_vec(), None).await.unwrap();
+		let second = GossipStore::bind("0.0.0.0:0".parse().unwrap(), Vec::new(), KEY.to_vec(), None).await.unwrap();
+		assert_ne!(first.state.node_id, second.state.node_id);
+		
+		second.add_sync("key", 2);
+		for datagram in GossipStore::collect_deltas(&second.state) {
+			GossipStore::merge(&first.state, GossipStore::verify(KEY, datagram.as_bytes()).unwrap());
+		}
+		
+		assert_eq!(first.add_sync("key", 1), 3);
+		
+		let configured = GossipStore::bind("0.0.0.0:0".parse().unwrap(), Vec::new(), KEY.to_vec(), Some(String::from("node-a"))).await.unwrap();
+		assert_eq!(configured.state.node_id, "node-a");
+		assert!(GossipStore::bind("0.0.0.0:0".parse().unwrap(), Vec::new(), KEY.to_vec(), Some(String::from("node a"))).await.is_err());
+	}
+	
+	#[test]
+	fn verifies_signatures() {
+		let signed = GossipStore::sign(KEY, &datagram("10.0.0.2:7946", 1, "key 5\n"));
+		assert_eq!(GossipStore::verify(KEY, signed.as_bytes()), Some(datagram("10.0.0.2:7946", 1, "key 5\n").as_str()));
+		assert_eq!(GossipStore::verify(b"other key", signed.as_bytes()), None);
+		assert_eq!(GossipStore::verify(KEY, signed.replace("key 5", "key 9").as_bytes()), None);
+		assert_eq!(GossipStore::verify(KEY, datagram("10.0.0.2:7946", 1, "key 5\n").as_bytes()), None);
+	}
+}
@@ -0,0 +1,203 @@
+use std::{
+	collections::HashMap,
+	sync::RwLock,
+	time::{Duration, Instant, SystemTime}
+};
+use crate::{
+	traits::{RateLimitStore, StoreBan, StoreEntry, StoreFuture},
+	RATE_LIMIT_WINDOW_SECS
+};
+
+pub struct MemoryStore {
+	map: RwLock<HashMap<String, (u8, Instant)>>,
+	// Key -> end of the ban
+	bans: RwLock<HashMap<String, SystemTime>>,
+}
+
+impl Default for MemoryStore {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl MemoryStore {
+	pub fn new() -> Self {
+		Self {
+			map: RwLock::new(HashMap::new()),
+			bans: RwLock::new(HashMap::new()),
+		}
+	}
+	
+	pub fn add_sync(&self, key: &str, hits: u8) -> u8 {
+		self.add_at(key, hits, Instant::now())
+	}
+	
+	pub fn get_sync(&self, key: &str) -> Option<u8> {
+		self.get_at(key, Instant::now())
+	}
+	
+	pub fn cleanup_sync(&self) {
+		self.cleanup_at(Instant::now());
+		
+		let now = SystemTime::now();
+		if let Ok(mut bans) = self.bans.write() {
+			bans.retain(|_, until| *until > now);
+		}
+	}
+	
+	pub fn ban_sync(&self, key: &str, until: SystemTime) {
+		if let Ok(mut bans) = self.bans.write() {
+			bans.insert(String::from(key), until);
+		}
+	}
+	
+	pub fn unban_sync(&self, key: &str) -> bool {
+		let now = SystemTime::now();
+		self.bans.write().ok().and_then(|mut bans| bans.remove(key)).is_some_and(|until| until > now)
+	}
+	
+	pub fn ban_ends_sync(&self, keys: &[String]) -> Vec<Option<SystemTime>> {
+		let now = SystemTime::now();
+		let Ok(bans) = self.bans.read() else {
+			return vec![None; keys.len()];
+		};
+		
+		keys.iter().map(|key| bans.get(key).copied().filter(|until| *until > now)).collect()
+	}
+	
+	pub fn bans_sync(&self) -> Vec<StoreBan> {
+		let now = SystemTime::now();
+		let Ok(bans) = self.bans.read() else {
+			return Vec::new();
+		};
+		
+		bans.iter().filter(|(_, until)| **until > now).map(|(key, until)| StoreBan { key: key.clone(), until: *until }).collect()
+	}
+	
+	/// Same as `add_sync`, with `now` read from a clock of the caller, e.g. a virtual one.
+	pub fn add_at(&self, key: &str, hits: u8, now: Instant) -> u8 {
+		let mut counter: u8 = hits;
+		if let Ok(mut map) = self.map.write() {
+			if let Some((count, last_time)) = map.get(key) && Self::is_alive(*last_time, now) {
+				counter = count.saturating_add(hits);
+			}
+			
+			map.insert(String::from(key), (counter, now));
+		}
+		
+		counter
+	}
+	
+	pub fn get_at(&self, key: &str, now: Instant) -> Option<u8> {
+		if let Ok(map) = self.map.read() && let Some((count, last_time)) = map.get(key) && Self::is_alive(*last_time, now) {
+			return Some(*count);
+		}
+		
+		None
+	}
+	
+	pub fn remove_sync(&self, key: &str) {
+		if let Ok(mut map) = self.map.write() {
+			map.remove(key);
+		}
+	}
+	
+	pub fn cleanup_at(&self, now: Instant) {
+		if let Ok(mut map) = self.map.write() {
+			map.retain(|_, &mut (_, last_time)| Self::is_alive(last_time, now));
+		}
+	}
+	
+	fn is_alive(time: Instant, now: Instant) -> bool {
+		now.saturating_duration_since(time) < Duration::from_secs(RATE_LIMIT_WINDOW_SECS)
+	}
+}
+
+impl RateLimitStore for MemoryStore {
+	fn add<'a>(&'a self, key: &'a str, hits: u8) -> StoreFuture<'a, u8> {
+		Box::pin(async move { Ok(self.add_sync(key, hits)) })
+	}
+	
+	fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<u8>> {
+		Box::pin(async move { Ok(self.get_sync(key)) })
+	}
+	
+	fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.remove_sync(key);
+			Ok(())
+		})
+	}
+	
+	fn cleanup(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			self.cleanup_sync();
+			Ok(())
+		})
+	}
+	
+	fn ban<'a>(&'a self, key: &'a str, until: SystemTime) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.ban_sync(key, until);
+			Ok(())
+		})
+	}
+	
+	fn unban<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool> {
+		Box::pin(async move { Ok(self.unban_sync(key)) })
+	}
+	
+	fn ban_ends<'a>(&'a self, keys: &'a [String]) -> StoreFuture<'a, Vec<Option<SystemTime>>> {
+		Box::pin(async move { Ok(self.ban_ends_sync(keys)) })
+	}
+	
+	fn bans(&self) -> StoreFuture<'_, Vec<StoreBan>> {
+		Box::pin(async move { Ok(self.bans_sync()) })
+	}
+	
+	fn snapshot(&self) -> Vec<StoreEntry> {
+		let now = SystemTime::now();
+		let mut entries: Vec<StoreEntry> = Vec::new();
+		if let Ok(map) = self.map.read() {
+			for (key, (count, last_time)) in map.iter() {
+				if Self::is_alive(*last_time, Instant::now()) {
+					entries.push(StoreEntry {
+						key: key.clone(),
+						count: *count,
+						last_seen: now - last_time.elapsed(),
+					});
+				}
+			}
+		}
+		
+		entries
+	}
+	
+	fn restore(&self, entries: Vec<StoreEntry>) {
+		let now = Instant::now();
+		if let Ok(mut map) = self.map.write() {
+			for entry in entries {
+				let age = entry.last_seen.elapsed().unwrap_or_default();
+				if age >= Duration::from_secs(RATE_LIMIT_WINDOW_SECS) {
+					continue;
+				}
+				
+				if let Some(last_time) = now.checked_sub(age) {
+					map.insert(entry.key, (entry.count, last_time));
+				}
+			}
+		}
+	}
+	
+	fn snapshot_bans(&self) -> Vec<StoreBan> {
+		self.bans_sync()
+	}
+	
+	fn restore_bans(&self, bans: Vec<StoreBan>) {
+		for ban in bans {
+			if ban.until > SystemTime::now() {
+				self.ban_sync(&ban.key, ban.until);
+			}
+		}
+	}
+}
@@ -0,0 +1,7 @@
+mod gossip;
+mod memory;
+mod redis;
+
+pub use gossip::GossipStore;
+pub use memory::MemoryStore;
+pub use redis::RedisStore;
@@ -0,0 +1,454 @@
+use std::{
+	io::{Error as IoError, ErrorKind},
+	sync::{atomic::{AtomicUsize, Ordering}, Mutex as StdMutex},
+	time::{Duration, SystemTime, UNIX_EPOCH}
+};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::TcpStream,
+	sync::{Mutex, MutexGuard},
+	time::{timeout, Instant}
+};
+use crate::{
+	stores::MemoryStore,
+	traits::{RateLimitStore, StoreBan, StoreEntry, StoreError, StoreFuture},
+	RATE_LIMIT_WINDOW_SECS
+};
+
+const KEY_PREFIX: &str = "rustrate:rl:";
+// Holds the end of the ban as unix milliseconds, and expires with it
+const BAN_PREFIX: &str = "rustrate:ban:";
+const POOL_SIZE: usize = 8;
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(250);
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+const SCAN_BATCH_SIZE: &str = "100";
+const MAX_LISTED_KEYS: usize = 10_000;
+// Longest bulk string read from the server, far more than a counter or a key ever takes
+const MAX_BULK_LENGTH: usize = 64 * 1024;
+
+/*
+ * INCRBY and PEXPIRE have to run as one unit, otherwise two instances hitting the
+ * same key can observe a counter whose TTL was never set.
+ */
+const ADD_SCRIPT: &str = "local c = redis.call('INCRBY', KEYS[1], ARGV[2]) redis.call('PEXPIRE', KEYS[1], ARGV[1]) return c";
+
+enum RespValue {
+	Simple(String),
+	// Error reply of a command, the connection itself is fine
+	Error(String),
+	Integer(i64),
+	Bulk(Option<Vec<u8>>),
+	Array(Vec<RespValue>),
+}
+
+type Connection = Option<BufReader<TcpStream>>;
+
+/// Shares counters between instances through a Redis-compatible (RESP) server.
+///
+/// Commands go over a small pool of connections, each carrying one command at a time,
+/// so concurrent requests do not queue behind a single socket. While the server is
+/// unreachable the counters are kept in a local `MemoryStore`, so each instance keeps
+/// enforcing its own limit until the backend comes back.
+pub struct RedisStore {
+	addr: String,
+	connections: Vec<Mutex<Connection>>,
+	next_connection: AtomicUsize,
+	down_until: StdMutex<Option<Instant>>,
+	fallback: MemoryStore,
+}
+
+impl RedisStore {
+	pub fn new(addr: impl Into<String>) -> Self {
+		Self {
+			addr: addr.into(),
+			connections: (0..POOL_SIZE).map(|_| Mutex::new(None)).collect(),
+			next_connection: AtomicUsize::new(0),
+			down_until: StdMutex::new(None),
+			fallback: MemoryStore::new(),
+		}
+	}
+	
+	async fn query(&self, args: &[&[u8]]) -> Result<RespValue, StoreError> {
+		if self.is_down() {
+			return Err(Box::new(IoError::new(ErrorKind::NotConnected, "redis backend is down")));
+		}
+		
+		let mut connection = self.connection().await;
+		let result = timeout(COMMAND_TIMEOUT, Self::round_trip(&self.addr, &mut connection, args)).await;
+		match result {
+			Ok(Ok(RespValue::Error(message))) => Err(StoreError::from(IoError::other(format!("redis error: {}", message)))),
+			Ok(Ok(value)) => Ok(value),
+			// Only a broken or stuck connection means the backend is down
+			Ok(Err(err)) => {
+				self.mark_down(&mut connection, &err);
+				Err(err)
+			},
+			Err(_) => {
+				let err: StoreError = Box::new(IoError::new(ErrorKind::TimedOut, "redis command timed out"));
+				self.mark_down(&mut connection, &err);
+				Err(err)
+			}
+		}
+	}
+	
+	/// An idle connection of the pool, or the next one in turn when they are all busy.
+	async fn connection(&self) -> MutexGuard<'_, Connection> {
+		for connection in &self.connections {
+			if let Ok(connection) = connection.try_lock() {
+				return connection;
+			}
+		}
+		
+		let index = self.next_connection.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+		self.connections[index].lock().await
+	}
+	
+	fn is_down(&self) -> bool {
+		let Ok(mut down_until) = self.down_until.lock() else {
+			return false;
+		};
+		
+		match *down_until {
+			Some(until) if Instant::now() < until => true,
+			Some(_) => {
+				*down_until = None;
+				false
+			},
+			None => false,
+		}
+	}
+	
+	fn mark_down(&self, connection: &mut Connection, err: &StoreError) {
+		eprintln!("Rate limit store unavailable, using local cache for {:?}: {}", RECONNECT_BACKOFF, err);
+		*connection = None;
+		if let Ok(mut down_until) = self.down_until.lock() {
+			*down_until = Some(Instant::now() + RECONNECT_BACKOFF);
+		}
+	}
+	
+	async fn round_trip(addr: &str, stream: &mut Connection, args: &[&[u8]]) -> Result<RespValue, StoreError> {
+		if stream.is_none() {
+			*stream = Some(BufReader::new(TcpStream::connect(addr).await?));
+		}
+		
+		let reader = stream.as_mut().unwrap();
+		reader.get_mut().write_all(&Self::encode(args)).await?;
+		
+		Self::read_value(reader).await
+	}
+	
+	fn encode(args: &[&[u8]]) -> Vec<u8> {
+		let mut buffer: Vec<u8> = format!("*{}\r\n", args.len()).into_bytes();
+		for arg in args {
+			buffer.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+			buffer.extend_from_slice(arg);
+			buffer.extend_from_slice(b"\r\n");
+		}
+		
+		buffer
+	}
+	
+	// Boxed because arrays nest, e.g. the reply of SCAN
+	fn read_value(reader: &mut BufReader<TcpStream>) -> StoreFuture<'_, RespValue> {
+		Box::pin(async move {
+			let line = Self::read_line(reader).await?;
+			let (kind, rest) = line.split_at(1);
+			
+			match kind {
+				"+" => Ok(RespValue::Simple(String::from(rest))),
+				"-" => Ok(RespValue::Error(String::from(rest))),
+				":" => Ok(RespValue::Integer(rest.parse()?)),
+				"$" => {
+					let length: i64 = rest.parse()?;
+					if length < 0 {
+						return Ok(RespValue::Bulk(None));
+					}
+					
+					if length as u64 > MAX_BULK_LENGTH as u64 {
+						return Err(StoreError::from(IoError::new(ErrorKind::InvalidData, "redis bulk reply too long")));
+					}
+					
+					let mut data: Vec<u8> = vec![0u8; length as usize + 2];
+					reader.read_exact(&mut data).await?;
+					data.truncate(length as usize);
+					Ok(RespValue::Bulk(Some(data)))
+				},
+				"*" => {
+					let length: i64 = rest.parse()?;
+					let mut values: Vec<RespValue> = Vec::new();
+					for _ in 0..length.max(0) {
+						values.push(Self::read_value(reader).await?);
+					}
+					
+					Ok(RespValue::Array(values))
+				},
+				_ => Err(StoreError::from(IoError::new(ErrorKind::InvalidData, "unexpected redis reply"))),
+			}
+		})
+	}
+	
+	async fn read_line(reader: &mut BufReader<TcpStream>) -> Result<String, StoreError> {
+		let mut line = String::new();
+		if reader.read_line(&mut line).await? == 0 {
+			return Err(Box::new(IoError::new(ErrorKind::UnexpectedEof, "redis closed the connection")));
+		}
+		
+		let line = line.trim_end_matches(['\r', '\n']);
+		if line.is_empty() {
+			return Err(Box::new(IoError::new(ErrorKind::InvalidData, "empty redis reply")));
+		}
+		
+		Ok(String::from(line))
+	}
+	
+	fn as_counter(value: RespValue) -> Option<u8> {
+		let count: i64 = match value {
+			RespValue::Integer(n) => n,
+			RespValue::Simple(s) => s.parse().ok()?,
+			RespValue::Bulk(Some(data)) => std::str::from_utf8(&data).ok()?.parse().ok()?,
+			RespValue::Bulk(None) | RespValue::Error(_) | RespValue::Array(_) => return None,
+		};
+		
+		Some(count.clamp(0, u8::MAX as i64) as u8)
+	}
+	
+	fn as_time(value: RespValue) -> Option<SystemTime> {
+		let millis: u64 = Self::as_string(value)?.parse().ok()?;
+		Some(UNIX_EPOCH + Duration::from_millis(millis)).filter(|until| *until > SystemTime::now())
+	}
+	
+	fn as_string(value: RespValue) -> Option<String> {
+		match value {
+			RespValue::Simple(s) => Some(s),
+			RespValue::Bulk(Some(data)) => String::from_utf8(data).ok(),
+			_ => None,
+		}
+	}
+	
+	/// Walks the keys under `prefix` with SCAN, which unlike KEYS never blocks the server
+	/// for long, and returns them without the prefix along with their values.
+	async fn scan(&self, prefix: &str) -> Result<Vec<(String, RespValue)>, StoreError> {
+		let pattern = format!("{}*", prefix);
+		let mut cursor = String::from("0");
+		let mut entries: Vec<(String, RespValue)> = Vec::new();
+		loop {
+			let args: [&[u8]; 6] = [b"SCAN", cursor.as_bytes(), b"MATCH", pattern.as_bytes(), b"COUNT", SCAN_BATCH_SIZE.as_bytes()];
+			let RespValue::Array(mut reply) = self.query(&args).await? else {
+				return Err(Box::new(IoError::new(ErrorKind::InvalidData, "unexpected redis reply to SCAN")));
+			};
+			
+			let (Some(RespValue::Array(keys)), Some(next_cursor)) = (reply.pop(), reply.pop().and_then(Self::as_string)) else {
+				return Err(Box::new(IoError::new(ErrorKind::InvalidData, "unexpected redis reply to SCAN")));
+			};
+			
+			let keys: Vec<String> = keys.into_iter().filter_map(Self::as_string).collect();
+			if !keys.is_empty() {
+				let mut args: Vec<&[u8]> = vec![b"MGET"];
+				args.extend(keys.iter().map(|key| key.as_bytes()));
+				if let RespValue::Array(values) = self.query(&args).await? {
+					for (key, value) in keys.iter().zip(values) {
+						entries.push((String::from(key.trim_start_matches(prefix)), value));
+					}
+				}
+			}
+			
+			cursor = next_cursor;
+			if cursor == "0" || entries.len() >= MAX_LISTED_KEYS {
+				return Ok(entries);
+			}
+		}
+	}
+}
+
+impl RateLimitStore for RedisStore {
+	fn add<'a>(&'a self, key: &'a str, hits: u8) -> StoreFuture<'a, u8> {
+		Box::pin(async move {
+			let redis_key = format!("{}{}", KEY_PREFIX, key);
+			let window = (RATE_LIMIT_WINDOW_SECS * 1000).to_string();
+			let hits_arg = hits.to_string();
+			let args: [&[u8]; 6] = [b"EVAL", ADD_SCRIPT.as_bytes(), b"1", redis_key.as_bytes(), window.as_bytes(), hits_arg.as_bytes()];
+			
+			match self.query(&args).await {
+				Ok(value) => Ok(Self::as_counter(value).unwrap_or(hits)),
+				Err(_) => Ok(self.fallback.add_sync(key, hits)),
+			}
+		})
+	}
+	
+	fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<u8>> {
+		Box::pin(async move {
+			let redis_key = format!("{}{}", KEY_PREFIX, key);
+			let args: [&[u8]; 2] = [b"GET", redis_key.as_bytes()];
+			
+			match self.query(&args).await {
+				Ok(value) => Ok(Self::as_counter(value)),
+				Err(_) => Ok(self.fallback.get_sync(key)),
+			}
+		})
+	}
+	
+	fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			let redis_key = format!("{}{}", KEY_PREFIX, key);
+			let args: [&[u8]; 2] = [b"DEL", redis_key.as_bytes()];
+			
+			// Cleared on both sides, so a reset holds however the backend is doing
+			self.fallback.remove_sync(key);
+			self.query(&args).await.map(|_| ())
+		})
+	}
+	
+	fn entries(&self) -> StoreFuture<'_, Vec<(String, u8)>> {
+		Box::pin(async move {
+			match self.scan(KEY_PREFIX).await {
+				Ok(entries) => Ok(entries.into_iter().filter_map(|(key, value)| Some((key, Self::as_counter(value)?))).collect()),
+				Err(_) => self.fallback.entries().await,
+			}
+		})
+	}
+	
+	fn cleanup(&self) -> StoreFuture<'_, ()> {
+		Box::pin(async move {
+			self.fallback.cleanup_sync();
+			Ok(())
+		})
+	}
+	
+	/// Also kept locally, so the ban holds on this instance through an outage.
+	fn ban<'a>(&'a self, key: &'a str, until: SystemTime) -> StoreFuture<'a, ()> {
+		Box::pin(async move {
+			self.fallback.ban_sync(key, until);
+			
+			let redis_key = format!("{}{}", BAN_PREFIX, key);
+			let until_millis = until.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis().to_string();
+			let ttl_millis = until.duration_since(SystemTime::now()).unwrap_or_default().as_millis().max(1).to_string();
+			let args: [&[u8]; 5] = [b"SET", redis_key.as_bytes(), until_millis.as_bytes(), b"PX", ttl_millis.as_bytes()];
+			
+			self.query(&args).await.map(|_| ())
+		})
+	}
+	
+	fn unban<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool> {
+		Box::pin(async move {
+			let redis_key = format!("{}{}", BAN_PREFIX, key);
+			let args: [&[u8]; 2] = [b"DEL", redis_key.as_bytes()];
+			
+			// Lifted on both sides, like a reset
+			let local = self.fallback.unban_sync(key);
+			let removed = self.query(&args).await?;
+			Ok(Self::as_counter(removed).is_some_and(|removed| removed > 0) || local)
+		})
+	}
+	
+	fn ban_ends<'a>(&'a self, keys: &'a [String]) -> StoreFuture<'a, Vec<Option<SystemTime>>> {
+		Box::pin(async move {
+			let redis_keys: Vec<String> = keys.iter().map(|key| format!("{}{}", BAN_PREFIX, key)).collect();
+			let mut args: Vec<&[u8]> = vec![b"MGET"];
+			args.extend(redis_keys.iter().map(|key| key.as_bytes()));
+			
+			match self.query(&args).await {
+				Ok(RespValue::Array(values)) if values.len() == keys.len() => Ok(values.into_iter().map(Self::as_time).collect()),
+				_ => Ok(self.fallback.ban_ends_sync(keys)),
+			}
+		})
+	}
+	
+	fn bans(&self) -> StoreFuture<'_, Vec<StoreBan>> {
+		Box::pin(async move {
+			match self.scan(BAN_PREFIX).await {
+				Ok(bans) => Ok(bans.into_iter().filter_map(|(key, value)| Some(StoreBan { key, until: Self::as_time(value)? })).collect()),
+				Err(_) => Ok(self.fallback.bans_sync()),
+			}
+		})
+	}
+	
+	// Redis keeps the shared counters and bans itself, only the ones held locally during an outage are saved
+	fn snapshot(&self) -> Vec<StoreEntry> {
+		self.fallback.snapshot()
+	}
+	
+	fn restore(&self, entries: Vec<StoreEntry>) {
+		self.fallback.restore(entries)
+	}
+	
+	fn snapshot_bans(&self) -> Vec<StoreBan> {
+		self.fallback.snapshot_bans()
+	}
+	
+	fn restore_bans(&self, bans: Vec<StoreBan>) {
+		self.fallback.restore_bans(bans)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::net::TcpListener;
+	
+	/// Serves `replies` in turn, one per command, on the first connection it gets.
+	async fn fake_server(replies: &'static [&'static str]) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap().to_string();
+		tokio::spawn(async move {
+			let (stream, _) = listener.accept().await.unwrap();
+			let mut reader = BufReader::new(stream);
+			for reply in replies {
+				let mut line = String::new();
+				reader.read_line(&mut line).await.unwrap();
+				let count: usize = line.trim_end()[1..].parse().unwrap();
+				for _ in 0..count * 2 {
+					line.clear();
+					reader.read_line(&mut line).await.unwrap();
+				}
+				
+				reader.get_mut().write_all(reply.as_bytes()).await.unwrap();
+			}
+		});
+		
+		addr
+	}
+	
+	#[tokio::test]
+	async fn keeps_the_backend_up_on_error_replies() {
+		let store = RedisStore::new(fake_server(&["-WRONGTYPE Operation against a key holding the wrong kind of value\r\n", ":7\r\n"]).await);
+		
+		assert!(store.query(&[b"GET", b"key"]).await.is_err());
+		assert!(!store.is_down());
+		
+		// Answered on the same connection, the fake server only ever accepts one
+		assert!(matches!(store.query(&[b"GET", b"key"]).await, Ok(RespValue::Integer(7))));
+	}
+	
+	#[tokio::test]
+	async fn refuses_oversized_bulk_replies() {
+		let store = RedisStore::new(fake_server(&["$9999999999\r\n"]).await);
+		
+		assert!(store.query(&[b"GET", b"key"]).await.is_err());
+		assert!(store.is_down());
+		assert_eq!(store.add("key", 1).await.unwrap(), 1);
+	}
+	
+	/// Needs a redis-server, at RUSTRATE_TEST_REDIS_ADDR or 127.0.0.1:6379:
+	/// cargo test -- --ignored
+	#[tokio::test]
+	#[ignore]
+	async fn shares_counters_and_bans_through_redis() {
+		let addr = std::env::var("RUSTRATE_TEST_REDIS_ADDR").unwrap_or_else(|_| String::from("127.0.0.1:6379"));
+		let store = RedisStore::new(addr.clone());
+		let other = RedisStore::new(addr);
+		let key = format!("test-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+		
+		assert_eq!(store.add(&key, 2).await.unwrap(), 2);
+		assert_eq!(other.add(&key, 3).await.unwrap(), 5);
+		assert_eq!(other.get(&key).await.unwrap(), Some(5));
+		assert!(!store.is_down());
+		
+		store.ban(&key, SystemTime::now() + Duration::from_secs(60)).await.unwrap();
+		assert!(other.ban_ends(std::slice::from_ref(&key)).await.unwrap()[0].is_some());
+		assert!(other.unban(&key).await.unwrap());
+		assert!(store.ban_ends(std::slice::from_ref(&key)).await.unwrap()[0].is_none());
+		
+		store.remove(&key).await.unwrap();
+		assert_eq!(other.get(&key).await.unwrap(), None);
+	}
+}
@@ -0,0 +1,7 @@
+use crate::core::Query;
+use crate::enums::QueryError;
+
+/// Types built from the query string of a request, see `HttpRequest::query_as`.
+pub trait FromQuery: Sized {
+	fn from_query(query: &Query) -> Result<Self, QueryError>;
+}
\ No newline at end of file
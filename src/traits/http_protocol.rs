@@ -1,6 +1,14 @@
 use std::error::Error;
-use crate::core::{HttpRequest};
+use std::future::Future;
+use crate::core::HttpRequest;
+use crate::enums::HttpStatusCode;
 
 pub trait HttpProtocol {
-	async fn handle(request: HttpRequest) -> Result<Vec<u8>, Box<dyn Error>>;
+	fn handle(request: HttpRequest) -> impl Future<Output = Result<Vec<u8>, Box<dyn Error>>>;
+	
+	/// Looks at the head of a request whose client waits for `100 Continue`, a status
+	/// refuses the body before it is sent.
+	fn check_expectation(_request: &HttpRequest) -> Result<(), HttpStatusCode> {
+		Ok(())
+	}
 }
\ No newline at end of file
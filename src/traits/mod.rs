@@ -1,2 +1,7 @@
 mod http_protocol;
-pub use http_protocol::HttpProtocol;
\ No newline at end of file
+mod rate_limit_store;
+mod from_query;
+
+pub use http_protocol::HttpProtocol;
+pub use from_query::FromQuery;
+pub use rate_limit_store::{RateLimitStore, StoreBan, StoreEntry, StoreError, StoreFuture};
@@ -0,0 +1,77 @@
+use std::{
+	error::Error,
+	future::Future,
+	pin::Pin,
+	time::SystemTime
+};
+
+pub type StoreError = Box<dyn Error + Send + Sync>;
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, StoreError>> + Send + 'a>>;
+
+/// Counter exported by a store, with its last hit converted to wall-clock time so
+/// it survives a restart.
+pub struct StoreEntry {
+	pub key: String,
+	pub count: u8,
+	pub last_seen: SystemTime,
+}
+
+/// Ban held by a store, ending at a wall-clock time so every instance and a restarted
+/// one agree on it.
+pub struct StoreBan {
+	pub key: String,
+	pub until: SystemTime,
+}
+
+/// Backend holding the per-key request counters and the bans used by `RateLimiter`.
+///
+/// A counter lives for `RATE_LIMIT_WINDOW_SECS` after its last hit; a hit on an
+/// expired counter starts again from 1. `GossipStore` is the exception, counting per
+/// fixed window of that length. A ban lives until its end.
+pub trait RateLimitStore: Send + Sync {
+	/// Registers `hits` hits for `key` and returns the updated counter.
+	fn add<'a>(&'a self, key: &'a str, hits: u8) -> StoreFuture<'a, u8>;
+	
+	/// Returns the live counter for `key`, if any.
+	fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<u8>>;
+	
+	/// Forgets the counter of `key`, as if it never got a hit.
+	fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()>;
+	
+	/// Lists the live counters, for inspection.
+	fn entries(&self) -> StoreFuture<'_, Vec<(String, u8)>> {
+		Box::pin(async move { Ok(self.snapshot().into_iter().map(|entry| (entry.key, entry.count)).collect()) })
+	}
+	
+	/// Drops expired counters and bans. Backends that expire keys on their own can ignore it.
+	fn cleanup(&self) -> StoreFuture<'_, ()>;
+	
+	/// Rejects every request of `key` until `until`, replacing any earlier ban.
+	fn ban<'a>(&'a self, key: &'a str, until: SystemTime) -> StoreFuture<'a, ()>;
+	
+	/// Lifts the ban of `key`, returning whether there was one.
+	fn unban<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool>;
+	
+	/// Ends of the live bans of `keys`, in the same order; looked up together since a
+	/// request is checked against all of its keys.
+	fn ban_ends<'a>(&'a self, keys: &'a [String]) -> StoreFuture<'a, Vec<Option<SystemTime>>>;
+	
+	/// Lists the live bans, for inspection.
+	fn bans(&self) -> StoreFuture<'_, Vec<StoreBan>>;
+	
+	/// Exports the live counters. Backends persisting state on their own return nothing.
+	fn snapshot(&self) -> Vec<StoreEntry> {
+		Vec::new()
+	}
+	
+	/// Loads counters from a previous snapshot, skipping the ones that expired meanwhile.
+	fn restore(&self, _entries: Vec<StoreEntry>) {}
+	
+	/// Exports the live bans, like `snapshot` does the counters.
+	fn snapshot_bans(&self) -> Vec<StoreBan> {
+		Vec::new()
+	}
+	
+	/// Loads bans from a previous snapshot, skipping the ones that ended meanwhile.
+	fn restore_bans(&self, _bans: Vec<StoreBan>) {}
+}
@@ -0,0 +1,166 @@
+use std::net::IpAddr;
+
+#[derive(Default)]
+struct Node {
+	children: [Option<usize>; 2],
+	terminal: bool,
+}
+
+/// Binary prefix trie of IPv4 and IPv6 networks; a lookup walks at most one node per
+/// address bit, however many networks are stored.
+pub struct CidrTrie {
+	v4: Vec<Node>,
+	v6: Vec<Node>,
+}
+
+impl Default for CidrTrie {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl CidrTrie {
+	pub fn new() -> Self {
+		Self {
+			v4: vec![Node::default()],
+			v6: vec![Node::default()],
+		}
+	}
+	
+	/// Inserts a network in CIDR notation (`10.0.0.0/8`, `2001:db8::/32`); a bare
+	/// address is taken as a single host.
+	pub fn insert(&mut self, cidr: &str) -> Result<(), String> {
+		let (addr, prefix) = match cidr.trim().split_once('/') {
+			Some((addr, prefix)) => (addr, Some(prefix)),
+			None => (cidr.trim(), None),
+		};
+		
+		let addr: IpAddr = addr.parse().map_err(|_| format!("invalid network address: {}", cidr))?;
+		let max_prefix: u32 = if addr.is_ipv4() { 32 } else { 128 };
+		let prefix: u32 = match prefix {
+			Some(prefix) => prefix.parse().map_err(|_| format!("invalid prefix length: {}", cidr))?,
+			None => max_prefix,
+		};
+		
+		if prefix > max_prefix {
+			return Err(format!("invalid prefix length: {}", cidr));
+		}
+		
+		// Lookups canonicalize v4-mapped addresses, so mapped networks are stored as IPv4
+		let (addr, prefix, max_prefix) = match addr.to_canonical() {
+			IpAddr::V4(v4) if addr.is_ipv6() && prefix >= 96 => (IpAddr::V4(v4), prefix - 96, 32),
+			_ => (addr, prefix, max_prefix),
+		};
+		
+		let (nodes, bits) = self.nodes_for(addr);
+		let mut current: usize = 0;
+		for i in 0..prefix {
+			let bit = ((bits >> (max_prefix - 1 - i)) & 1) as usize;
+			current = match nodes[current].children[bit] {
+				Some(next) => next,
+				None => {
+					nodes.push(Node::default());
+					let next = nodes.len() - 1;
+					nodes[current].children[bit] = Some(next);
+					next
+				}
+			};
+		}
+		
+		nodes[current].terminal = true;
+		
+		Ok(())
+	}
+	
+	pub fn contains(&self, addr: IpAddr) -> bool {
+		let addr = addr.to_canonical();
+		let (nodes, bits, max_prefix): (&Vec<Node>, u128, u32) = match addr {
+			IpAddr::V4(v4) => (&self.v4, u32::from(v4) as u128, 32),
+			IpAddr::V6(v6) => (&self.v6, u128::from(v6), 128),
+		};
+		
+		let mut current: usize = 0;
+		for i in 0..max_prefix {
+			if nodes[current].terminal {
+				return true;
+			}
+			
+			let bit = ((bits >> (max_prefix - 1 - i)) & 1) as usize;
+			match nodes[current].children[bit] {
+				Some(next) => current = next,
+				None => return false,
+			}
+		}
+		
+		nodes[current].terminal
+	}
+	
+	fn nodes_for(&mut self, addr: IpAddr) -> (&mut Vec<Node>, u128) {
+		match addr {
+			IpAddr::V4(v4) => (&mut self.v4, u32::from(v4) as u128),
+			IpAddr::V6(v6) => (&mut self.v6, u128::from(v6)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn trie(networks: &[&str]) -> CidrTrie {
+		let mut trie = CidrTrie::new();
+		for network in networks {
+			trie.insert(network).unwrap();
+		}
+		
+		trie
+	}
+	
+	fn ip(addr: &str) -> IpAddr {
+		addr.parse().unwrap()
+	}
+	
+	#[test]
+	fn matches_prefixes() {
+		let trie = trie(&["10.0.0.0/8", "192.168.1.0/24", "203.0.113.7", "2001:db8::/32"]);
+		
+		assert!(trie.contains(ip("10.255.0.1")));
+		assert!(trie.contains(ip("192.168.1.200")));
+		assert!(!trie.contains(ip("192.168.2.1")));
+		assert!(trie.contains(ip("203.0.113.7")));
+		assert!(!trie.contains(ip("203.0.113.8")));
+		assert!(trie.contains(ip("2001:db8:1::1")));
+		assert!(!trie.contains(ip("2001:db9::1")));
+		assert!(!trie.contains(ip("11.0.0.1")));
+	}
+	
+	#[test]
+	fn matches_everything_under_a_zero_prefix() {
+		let trie = trie(&["0.0.0.0/0"]);
+		
+		assert!(trie.contains(ip("1.2.3.4")));
+		assert!(trie.contains(ip("::ffff:1.2.3.4")));
+		assert!(!trie.contains(ip("::1")));
+	}
+	
+	#[test]
+	fn matches_v4_mapped_entries() {
+		let trie = trie(&["::ffff:10.0.0.0/104", "::ffff:198.51.100.9"]);
+		
+		assert!(trie.contains(ip("10.1.2.3")));
+		assert!(trie.contains(ip("::ffff:10.1.2.3")));
+		assert!(trie.contains(ip("198.51.100.9")));
+		assert!(!trie.contains(ip("198.51.100.10")));
+	}
+	
+	#[test]
+	fn rejects_invalid_networks() {
+		let mut trie = CidrTrie::new();
+		
+		assert!(trie.insert("10.0.0.0/33").is_err());
+		assert!(trie.insert("2001:db8::/129").is_err());
+		assert!(trie.insert("10.0.0.0/x").is_err());
+		assert!(trie.insert("10.0.0/8").is_err());
+	}
+}
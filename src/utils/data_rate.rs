@@ -0,0 +1,248 @@
+use std::{
+	error::Error,
+	io::{Error as IoError, ErrorKind},
+	time::Duration
+};
+use bytes::BytesMut;
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+	time::{timeout_at, Instant}
+};
+use crate::{
+	enums::HttpError,
+	MIN_DATA_RATE_BYTES_PER_SEC,
+	MIN_DATA_RATE_WINDOW_SECS
+};
+
+const READ_CHUNK_SIZE: usize = 8192;
+const WRITE_CHUNK_SIZE: usize = 8192;
+
+/// Throughput of a transfer measured over consecutive windows of
+/// `MIN_DATA_RATE_WINDOW_SECS`; a window moving less than
+/// `MIN_DATA_RATE_BYTES_PER_SEC` on average marks the peer as too slow.
+pub struct DataRate {
+	window_start: Instant,
+	window_bytes: u64,
+}
+
+impl Default for DataRate {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl DataRate {
+	pub fn new() -> Self {
+		Self {
+			window_start: Instant::now(),
+			window_bytes: 0,
+		}
+	}
+	
+	pub fn window_end(&self) -> Instant {
+		self.window_start + Duration::from_secs(MIN_DATA_RATE_WINDOW_SECS)
+	}
+	
+	/// Records `bytes` and returns `false` once a finished window fell below the minimum rate.
+	pub fn record(&mut self, bytes: usize) -> bool {
+		self.window_bytes += bytes as u64;
+		
+		let now = Instant::now();
+		if now < self.window_end() {
+			return true;
+		}
+		
+		let elapsed = now.duration_since(self.window_start).as_millis().max(1) as u64;
+		let fast_enough = self.window_bytes * 1000 / elapsed >= MIN_DATA_RATE_BYTES_PER_SEC;
+		
+		self.window_start = now;
+		self.window_bytes = 0;
+		
+		fast_enough
+	}
+}
+
+/// Fills `buf` before `deadline`, failing with `HttpError::SlowClient` when the peer
+/// sends slower than the minimum data rate and `HttpError::RequestTimeout` past the deadline.
+pub async fn read_exact_with_min_rate<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8], deadline: Instant) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let mut rate = DataRate::new();
+	let mut filled: usize = 0;
+	
+	while filled < buf.len() {
+		match timeout_at(rate.window_end().min(deadline), reader.read(&mut buf[filled..])).await {
+			Ok(Ok(0)) => return Err(Box::new(IoError::from(ErrorKind::UnexpectedEof))),
+			Ok(Ok(n)) => {
+				filled += n;
+				if !rate.record(n) {
+					return Err(Box::new(HttpError::SlowClient));
+				}
+			},
+			Ok(Err(e)) => return Err(Box::new(e)),
+			Err(_) => {
+				if Instant::now() >= deadline {
+					return Err(Box::new(HttpError::RequestTimeout));
+				}
+				
+				if !rate.record(0) {
+					return Err(Box::new(HttpError::SlowClient));
+				}
+			}
+		}
+	}
+	
+	Ok(())
+}
+
+/// Appends the next bytes the peer sends to `buf`, for reads of unknown length such as
+/// chunked bodies. `rate` is shared by the successive reads of one transfer and the
+/// errors are those of `read_exact_with_min_rate`.
+pub async fn read_buf_with_min_rate<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut BytesMut, rate: &mut DataRate, deadline: Instant) -> Result<usize, Box<dyn Error + Send + Sync>> {
+	buf.reserve(READ_CHUNK_SIZE);
+	loop {
+		match timeout_at(rate.window_end().min(deadline), reader.read_buf(buf)).await {
+			Ok(Ok(0)) => return Err(Box::new(IoError::from(ErrorKind::UnexpectedEof))),
+			Ok(Ok(n)) => {
+				if !rate.record(n) {
+					return Err(Box::new(HttpError::SlowClient));
+				}
+				
+				return Ok(n);
+			},
+			Ok(Err(e)) => return Err(Box::new(e)),
+			Err(_) => {
+				if Instant::now() >= deadline {
+					return Err(Box::new(HttpError::RequestTimeout));
+				}
+				
+				if !rate.record(0) {
+					return Err(Box::new(HttpError::SlowClient));
+				}
+			}
+		}
+	}
+}
+
+/// Writes `buf` in chunks before `deadline`, failing with `HttpError::SlowClient` when
+/// the peer reads slower than the minimum data rate or the deadline passes.
+pub async fn write_all_with_min_rate<W: AsyncWrite + Unpin>(writer: &mut W, buf: &[u8], deadline: Instant) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let mut rate = DataRate::new();
+	
+	for chunk in buf.chunks(WRITE_CHUNK_SIZE) {
+		let mut written: usize = 0;
+		while written < chunk.len() {
+			match timeout_at(rate.window_end().min(deadline), writer.write(&chunk[written..])).await {
+				Ok(Ok(0)) => return Err(Box::new(IoError::from(ErrorKind::WriteZero))),
+				Ok(Ok(n)) => {
+					written += n;
+					if !rate.record(n) {
+						return Err(Box::new(HttpError::SlowClient));
+					}
+				},
+				Ok(Err(e)) => return Err(Box::new(e)),
+				Err(_) => {
+					if Instant::now() >= deadline || !rate.record(0) {
+						return Err(Box::new(HttpError::SlowClient));
+					}
+				}
+			}
+		}
+	}
+	
+	writer.flush().await?;
+	
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::io::duplex;
+	
+	fn window() -> Duration {
+		Duration::from_secs(MIN_DATA_RATE_WINDOW_SECS)
+	}
+	
+	fn rate_started_ago(elapsed: Duration) -> DataRate {
+		DataRate {
+			window_start: Instant::now() - elapsed,
+			window_bytes: 0,
+		}
+	}
+	
+	#[test]
+	fn judges_a_window_only_once_it_is_over() {
+		let mut rate = DataRate::new();
+		assert!(rate.record(0));
+		assert!(rate.record(1));
+	}
+	
+	#[test]
+	fn tells_slow_windows_from_fast_ones() {
+		let bytes = (MIN_DATA_RATE_BYTES_PER_SEC * MIN_DATA_RATE_WINDOW_SECS) as usize;
+		
+		let mut rate = rate_started_ago(window());
+		assert!(rate.record(bytes));
+		
+		let mut rate = rate_started_ago(window());
+		assert!(!rate.record(bytes / 2));
+	}
+	
+	#[test]
+	fn starts_a_new_window_after_judging_one() {
+		let mut rate = rate_started_ago(window());
+		assert!(!rate.record(0));
+		assert_eq!(rate.window_bytes, 0);
+		assert!(rate.window_end() > Instant::now());
+		assert!(rate.record(0));
+	}
+	
+	#[tokio::test]
+	async fn reads_what_arrives_in_time() {
+		let (mut client, mut server) = duplex(64);
+		client.write_all(b"hello").await.unwrap();
+		
+		let mut buf = [0u8; 5];
+		read_exact_with_min_rate(&mut server, &mut buf, Instant::now() + window()).await.unwrap();
+		assert_eq!(&buf, b"hello");
+	}
+	
+	#[tokio::test]
+	async fn times_out_at_the_deadline() {
+		let (mut client, mut server) = duplex(64);
+		client.write_all(b"he").await.unwrap();
+		
+		let mut buf = [0u8; 5];
+		let err = read_exact_with_min_rate(&mut server, &mut buf, Instant::now() + Duration::from_millis(50)).await.unwrap_err();
+		assert!(matches!(err.downcast_ref::<HttpError>(), Some(HttpError::RequestTimeout)));
+	}
+	
+	#[tokio::test]
+	async fn fails_on_a_closed_peer() {
+		let (client, mut server) = duplex(64);
+		drop(client);
+		
+		let mut buf = BytesMut::new();
+		let err = read_buf_with_min_rate(&mut server, &mut buf, &mut DataRate::new(), Instant::now() + window()).await.unwrap_err();
+		assert_eq!(err.downcast_ref::<IoError>().map(IoError::kind), Some(ErrorKind::UnexpectedEof));
+	}
+	
+	#[tokio::test]
+	async fn fails_a_trickling_peer() {
+		let (mut client, mut server) = duplex(64);
+		client.write_all(b"a").await.unwrap();
+		
+		// A whole window has passed with a single byte, far below the minimum rate
+		let mut buf = BytesMut::new();
+		let mut rate = rate_started_ago(window());
+		let err = read_buf_with_min_rate(&mut server, &mut buf, &mut rate, Instant::now() + window()).await.unwrap_err();
+		assert!(matches!(err.downcast_ref::<HttpError>(), Some(HttpError::SlowClient)));
+	}
+	
+	#[tokio::test]
+	async fn fails_a_peer_that_stops_reading() {
+		let (_client, mut server) = duplex(16);
+		
+		let err = write_all_with_min_rate(&mut server, &[0u8; 64], Instant::now() + Duration::from_millis(50)).await.unwrap_err();
+		assert!(matches!(err.downcast_ref::<HttpError>(), Some(HttpError::SlowClient)));
+	}
+}
@@ -1,4 +1,6 @@
 use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Result as IoResult};
 use chrono::Utc;
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 use rustls_pki_types::pem::PemObject;
@@ -10,7 +12,46 @@ pub fn http_date_string() -> String {
 }
 
 pub fn sanitize_header_value(value: &str) -> String {
-	value.replace('\r', "").replace('\n', "")
+	value.replace(['\r', '\n'], "")
+}
+
+/// `length` bytes from the kernel's random source.
+pub fn random_bytes(length: usize) -> IoResult<Vec<u8>> {
+	let mut bytes = vec![0u8; length];
+	File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+	if !hex.len().is_multiple_of(2) {
+		return None;
+	}
+	
+	(0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Quotes `value` as a JSON string.
+pub fn json_string(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len() + 2);
+	quoted.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+			c => quoted.push(c),
+		}
+	}
+	
+	quoted.push('"');
+	quoted
 }
 
 pub fn load_tls_config() -> Result<ServerConfig, Box<dyn Error>> {
@@ -20,7 +61,7 @@ pub fn load_tls_config() -> Result<ServerConfig, Box<dyn Error>> {
 			item.unwrap()
 		})
 		.collect();
-
+	
 	let key = PrivateKeyDer::from_pem_file("certs/localhost-key.pem").unwrap();
 	
 	let mut config = ServerConfig::builder()
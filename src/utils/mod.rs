@@ -1 +1,3 @@
-pub mod helper;
\ No newline at end of file
+pub mod helper;
+pub mod data_rate;
+pub mod cidr_trie;